@@ -0,0 +1,127 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use super::extract::{self, ExtractReport, StreamOutcome};
+use super::queue::JobControl;
+use super::DownloadError;
+
+// Bytes needed to recognise every supported format
+pub const MAGIC_LEN: usize = 6;
+// Up to the general purpose flags of a zip's first local file header
+const ZIP_FLAGS_END: usize = 8;
+// Local header flag: sizes follow the entry data instead of preceding it
+const ZIP_DATA_DESCRIPTOR: u8 = 0x08;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+    TarZst,
+    SevenZip,
+}
+
+impl ArchiveFormat {
+    // Names accepted in the manifest's `archive_format`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "zip" => Some(ArchiveFormat::Zip),
+            "tar.gz" | "tgz" => Some(ArchiveFormat::TarGz),
+            "tar.zst" | "tzst" => Some(ArchiveFormat::TarZst),
+            "7z" => Some(ArchiveFormat::SevenZip),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::TarZst => "tar.zst",
+            ArchiveFormat::SevenZip => "7z",
+        }
+    }
+
+    // Recognise a format from the first bytes of the file
+    pub fn detect(head: &[u8]) -> Option<Self> {
+        if head.starts_with(b"PK\x03\x04") || head.starts_with(b"PK\x05\x06") {
+            Some(ArchiveFormat::Zip)
+        } else if head.starts_with(&[0x1f, 0x8b]) {
+            Some(ArchiveFormat::TarGz)
+        } else if head.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(ArchiveFormat::TarZst)
+        } else if head.starts_with(b"7z\xbc\xaf\x27\x1c") {
+            Some(ArchiveFormat::SevenZip)
+        } else {
+            None
+        }
+    }
+}
+
+// The manifest's `archive_format` wins; otherwise the magic bytes decide
+pub fn resolve_format(declared: Option<&str>, head: &[u8]) -> Result<ArchiveFormat, String> {
+    match declared {
+        Some(name) => ArchiveFormat::from_name(name).ok_or_else(|| format!("Unsupported archive format '{}'", name)),
+        None => ArchiveFormat::detect(head).ok_or_else(|| "Unrecognized archive format".to_string()),
+    }
+}
+
+fn read_head(reader: &mut dyn Read, len: usize) -> Result<Vec<u8>, String> {
+    let mut head = Vec::with_capacity(len);
+    reader.take(len as u64).read_to_end(&mut head).map_err(|e| e.to_string())?;
+    Ok(head)
+}
+
+// Extract an archive file of any supported format into `dest` with the same safety rules
+pub fn extract_file(path: &Path, declared: Option<&str>, dest: &Path, control: &JobControl) -> Result<ExtractReport, DownloadError> {
+    let archive_size = fs::metadata(path).map_err(|e| e.to_string())?.len();
+    let limit = extract::uncompressed_limit(archive_size);
+    let mut file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let format = resolve_format(declared, &read_head(&mut file, MAGIC_LEN)?)?;
+    println!("Extracting {:?} as {}", path, format.as_str());
+
+    match format {
+        ArchiveFormat::Zip => extract::extract_zip(path, dest, limit, control),
+        ArchiveFormat::TarGz => {
+            let mut decoder = flate2::read::GzDecoder::new(fs::File::open(path).map_err(|e| e.to_string())?);
+            extract::extract_tar(&mut decoder, dest, limit, control)
+        }
+        ArchiveFormat::TarZst => {
+            let mut decoder = zstd::stream::read::Decoder::new(fs::File::open(path).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+            extract::extract_tar(&mut decoder, dest, limit, control)
+        }
+        #[cfg(feature = "sevenz")]
+        ArchiveFormat::SevenZip => extract::extract_7z(path, dest, limit, control),
+        #[cfg(not(feature = "sevenz"))]
+        ArchiveFormat::SevenZip => Err("7z archives are not supported by this launcher build".into()),
+    }
+}
+
+// Extract an archive while it is being read front to back. 7z needs random access, and some
+// zips only describe entries at the end; both are reported as unsupported, from the first bytes
+// where possible so nothing has been written yet.
+pub fn extract_stream(reader: &mut dyn Read, declared: Option<&str>, dest: &Path, limit: u64, control: &JobControl) -> Result<StreamOutcome, DownloadError> {
+    let head = read_head(reader, ZIP_FLAGS_END)?;
+    let format = resolve_format(declared, &head)?;
+    if format == ArchiveFormat::Zip && head.len() == ZIP_FLAGS_END && head[6] & ZIP_DATA_DESCRIPTOR != 0 {
+        return Ok(StreamOutcome::Unsupported("zip entries use data descriptors".to_string()));
+    }
+    let mut reader = io::Cursor::new(head).chain(reader);
+
+    match format {
+        ArchiveFormat::Zip => extract::extract_zip_stream(&mut reader, dest, limit),
+        ArchiveFormat::TarGz => {
+            let mut decoder = flate2::read::GzDecoder::new(&mut reader);
+            let report = extract::extract_tar(&mut decoder, dest, limit, control)?;
+            io::copy(&mut reader, &mut io::sink()).map_err(|e| e.to_string())?;
+            Ok(StreamOutcome::Extracted(report))
+        }
+        ArchiveFormat::TarZst => {
+            let mut decoder = zstd::stream::read::Decoder::new(&mut reader).map_err(|e| e.to_string())?;
+            let report = extract::extract_tar(&mut decoder, dest, limit, control)?;
+            io::copy(&mut reader, &mut io::sink()).map_err(|e| e.to_string())?;
+            Ok(StreamOutcome::Extracted(report))
+        }
+        ArchiveFormat::SevenZip => Ok(StreamOutcome::Unsupported("7z archives need random access".to_string())),
+    }
+}
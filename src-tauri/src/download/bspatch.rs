@@ -0,0 +1,209 @@
+use std::cmp;
+use std::fs;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use bzip2::read::BzDecoder;
+use sha2::{Digest, Sha256};
+
+// Applies patches in the classic bsdiff 4 format ("BSDIFF40"): a 32 byte header followed by
+// three bzip2 streams of control triples, bytes added to the old file, and new bytes.
+const MAGIC: &[u8] = b"BSDIFF40";
+const HEADER_LEN: u64 = 32;
+const BUFFER_SIZE: usize = 64 * 1024;
+
+// bsdiff stores integers as sign and magnitude, little endian
+fn offtin(buf: [u8; 8]) -> i64 {
+    let mut value = (buf[7] & 0x7f) as i64;
+    for byte in buf[..7].iter().rev() {
+        value = value * 256 + *byte as i64;
+    }
+    if buf[7] & 0x80 != 0 {
+        -value
+    } else {
+        value
+    }
+}
+
+fn read_offt(reader: &mut dyn Read) -> Result<i64, String> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf).map_err(|e| format!("Corrupt patch: {}", e))?;
+    Ok(offtin(buf))
+}
+
+// One of the three compressed blocks of the patch file
+fn open_block(patch_path: &Path, start: u64, len: Option<u64>) -> Result<BzDecoder<Box<dyn Read>>, String> {
+    let mut file = fs::File::open(patch_path).map_err(|e| e.to_string())?;
+    file.seek(SeekFrom::Start(start)).map_err(|e| e.to_string())?;
+    let reader: Box<dyn Read> = match len {
+        Some(len) => Box::new(file.take(len)),
+        None => Box::new(file),
+    };
+    Ok(BzDecoder::new(reader))
+}
+
+// Read `buf.len()` bytes of the old file starting at `position`; bytes outside it count as zero
+fn read_old(old: &mut fs::File, old_size: u64, position: i64, buf: &mut [u8]) -> Result<(), String> {
+    for byte in buf.iter_mut() {
+        *byte = 0;
+    }
+    let start = cmp::max(position, 0);
+    let end = cmp::min(position.saturating_add(buf.len() as i64), old_size as i64);
+    if start >= end {
+        return Ok(());
+    }
+    let offset = (start - position) as usize;
+    old.seek(SeekFrom::Start(start as u64)).map_err(|e| e.to_string())?;
+    old.read_exact(&mut buf[offset..offset + (end - start) as usize]).map_err(|e| e.to_string())
+}
+
+// Rebuild a file from `old_path` and a patch into `output_path`, streaming so large assets are
+// never held in memory. Returns the SHA-256 (hex) and size of the output.
+pub fn apply(old_path: &Path, patch_path: &Path, output_path: &Path) -> Result<(String, u64), String> {
+    let mut header = [0u8; HEADER_LEN as usize];
+    let mut patch = fs::File::open(patch_path).map_err(|e| e.to_string())?;
+    let patch_size = patch.metadata().map_err(|e| e.to_string())?.len();
+    patch.read_exact(&mut header).map_err(|e| format!("Corrupt patch: {}", e))?;
+    if &header[..8] != MAGIC {
+        return Err("Not a bsdiff patch".to_string());
+    }
+    let mut field = [0u8; 8];
+    field.copy_from_slice(&header[8..16]);
+    let ctrl_len = offtin(field);
+    field.copy_from_slice(&header[16..24]);
+    let diff_len = offtin(field);
+    field.copy_from_slice(&header[24..32]);
+    let new_size = offtin(field);
+    if ctrl_len < 0 || diff_len < 0 || new_size < 0 || (ctrl_len as u64).saturating_add(diff_len as u64) > patch_size - HEADER_LEN {
+        return Err("Corrupt patch header".to_string());
+    }
+    let (ctrl_len, diff_len, new_size) = (ctrl_len as u64, diff_len as u64, new_size as u64);
+
+    let mut ctrl = open_block(patch_path, HEADER_LEN, Some(ctrl_len))?;
+    let mut diff = open_block(patch_path, HEADER_LEN + ctrl_len, Some(diff_len))?;
+    let mut extra = open_block(patch_path, HEADER_LEN + ctrl_len + diff_len, None)?;
+
+    let mut old = fs::File::open(old_path).map_err(|e| e.to_string())?;
+    let old_size = old.metadata().map_err(|e| e.to_string())?.len();
+    let mut output = BufWriter::new(fs::File::create(output_path).map_err(|e| e.to_string())?);
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; BUFFER_SIZE];
+    let mut old_buf = vec![0u8; BUFFER_SIZE];
+
+    let mut new_pos = 0u64;
+    let mut old_pos = 0i64;
+    while new_pos < new_size {
+        let add_len = read_offt(&mut ctrl)?;
+        let copy_len = read_offt(&mut ctrl)?;
+        let seek_len = read_offt(&mut ctrl)?;
+        let end = (add_len as u64).checked_add(copy_len as u64).and_then(|len| len.checked_add(new_pos));
+        if add_len < 0 || copy_len < 0 || end.map(|end| end > new_size).unwrap_or(true) {
+            return Err("Corrupt patch: control data out of range".to_string());
+        }
+
+        // Diff bytes are added to the old file's bytes at the same position
+        let mut remaining = add_len as u64;
+        while remaining > 0 {
+            let n = cmp::min(remaining, BUFFER_SIZE as u64) as usize;
+            diff.read_exact(&mut buf[..n]).map_err(|e| format!("Corrupt patch: {}", e))?;
+            read_old(&mut old, old_size, old_pos, &mut old_buf[..n])?;
+            for (byte, old_byte) in buf[..n].iter_mut().zip(old_buf[..n].iter()) {
+                *byte = byte.wrapping_add(*old_byte);
+            }
+            output.write_all(&buf[..n]).map_err(|e| e.to_string())?;
+            hasher.update(&buf[..n]);
+            old_pos += n as i64;
+            remaining -= n as u64;
+        }
+        new_pos += add_len as u64;
+
+        // Extra bytes are new data copied as is
+        let mut remaining = copy_len as u64;
+        while remaining > 0 {
+            let n = cmp::min(remaining, BUFFER_SIZE as u64) as usize;
+            extra.read_exact(&mut buf[..n]).map_err(|e| format!("Corrupt patch: {}", e))?;
+            output.write_all(&buf[..n]).map_err(|e| e.to_string())?;
+            hasher.update(&buf[..n]);
+            remaining -= n as u64;
+        }
+        new_pos += copy_len as u64;
+        old_pos = old_pos.checked_add(seek_len).ok_or("Corrupt patch: control data out of range")?;
+    }
+    output.flush().map_err(|e| e.to_string())?;
+
+    Ok((hex::encode(hasher.finalize()), new_pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bzip2::write::BzEncoder;
+    use bzip2::Compression;
+
+    fn offtout(value: i64) -> [u8; 8] {
+        let mut buf = value.unsigned_abs().to_le_bytes();
+        if value < 0 {
+            buf[7] |= 0x80;
+        }
+        buf
+    }
+
+    fn bz(data: &[u8]) -> Vec<u8> {
+        let mut encoder = BzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn make_patch(controls: &[(i64, i64, i64)], diff: &[u8], extra: &[u8], new_size: i64) -> Vec<u8> {
+        let mut ctrl = Vec::new();
+        for (add_len, copy_len, seek_len) in controls {
+            ctrl.extend_from_slice(&offtout(*add_len));
+            ctrl.extend_from_slice(&offtout(*copy_len));
+            ctrl.extend_from_slice(&offtout(*seek_len));
+        }
+        let (ctrl, diff) = (bz(&ctrl), bz(diff));
+        let mut patch = MAGIC.to_vec();
+        patch.extend_from_slice(&offtout(ctrl.len() as i64));
+        patch.extend_from_slice(&offtout(diff.len() as i64));
+        patch.extend_from_slice(&offtout(new_size));
+        patch.extend(ctrl);
+        patch.extend(diff);
+        patch.extend(bz(extra));
+        patch
+    }
+
+    // Applies `patch` to `old` in a scratch folder and returns the result with the output bytes
+    fn run(name: &str, old: &[u8], patch: &[u8]) -> Result<(String, u64, Vec<u8>), String> {
+        let dir = std::env::temp_dir().join(format!("bspatch-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (old_path, patch_path, output_path) = (dir.join("old"), dir.join("patch"), dir.join("new"));
+        fs::write(&old_path, old).unwrap();
+        fs::write(&patch_path, patch).unwrap();
+        let result = apply(&old_path, &patch_path, &output_path)
+            .map(|(sha256, size)| (sha256, size, fs::read(&output_path).unwrap_or_default()));
+        let _ = fs::remove_dir_all(&dir);
+        result
+    }
+
+    #[test]
+    fn applies_patch() {
+        // "abc" with one byte changed, "XY" added, then "ef" after skipping "d"
+        let patch = make_patch(&[(3, 2, 1), (2, 0, 0)], &[0, 1, 0, 0, 0], b"XY", 7);
+        let (sha256, size, output) = run("apply", b"abcdef", &patch).unwrap();
+        assert_eq!(output, b"accXYef");
+        assert_eq!(size, 7);
+        assert_eq!(sha256, hex::encode(Sha256::digest(b"accXYef")));
+    }
+
+    #[test]
+    fn rejects_truncated_header() {
+        let error = run("header", b"abcdef", b"BSDIFF40\0\0\0\0").unwrap_err();
+        assert!(error.starts_with("Corrupt patch"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_control_past_new_size() {
+        let patch = make_patch(&[(3, 2, 0)], &[0, 0, 0], b"XY", 4);
+        let error = run("range", b"abcdef", &patch).unwrap_err();
+        assert_eq!(error, "Corrupt patch: control data out of range");
+    }
+}
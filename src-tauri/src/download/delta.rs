@@ -0,0 +1,306 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use futures_util::StreamExt;
+use sha2::{Digest, Sha256};
+
+use super::bspatch;
+use super::extract::{enclosed_path, ExtractReport};
+use super::integrity::ExpectedArchive;
+use super::progress::{DownloadPhase, ProgressReporter};
+use super::queue::JobControl;
+use super::DownloadError;
+use crate::manifest::files::{self, FileEntry, FileManifest, FilePatch};
+
+// One bsdiff patch to apply and the file it has to produce
+#[derive(Debug, Clone)]
+pub struct PatchStep {
+    pub url: String,
+    pub patch: FilePatch,
+    pub to_sha256: String,
+    pub to_size: u64,
+}
+
+// Patches rebuilding a target file from the installed file at the same path, one per version
+// the file changed in
+#[derive(Debug, Clone)]
+pub struct PatchChain {
+    pub source: FileEntry,
+    pub steps: Vec<PatchStep>,
+}
+
+impl PatchChain {
+    fn size(&self) -> u64 {
+        self.steps.iter().map(|s| s.patch.size).sum()
+    }
+}
+
+// What it takes to turn an installed build into the target build
+#[derive(Debug, Clone, Default)]
+pub struct DeltaPlan {
+    // Target files whose content is already installed, with the installed path to copy from
+    pub reuse: Vec<(FileEntry, String)>,
+    pub patch: Vec<(FileEntry, PatchChain)>,
+    pub download: Vec<FileEntry>,
+    // Installed files the target build no longer has
+    pub removed: Vec<String>,
+}
+
+impl DeltaPlan {
+    pub fn download_size(&self) -> u64 {
+        let patches: u64 = self.patch.iter().map(|(_, chain)| chain.size()).sum();
+        patches + self.download.iter().map(|f| f.size).sum::<u64>()
+    }
+}
+
+// Patch URLs may be relative to the file manifest's base URL
+fn resolve_url(base_url: Option<&str>, url: &str) -> Option<String> {
+    if let Ok(url) = reqwest::Url::parse(url) {
+        return Some(url.to_string());
+    }
+    let base_url = base_url?;
+    let base = if base_url.ends_with('/') { base_url.to_string() } else { format!("{}/", base_url) };
+    reqwest::Url::parse(&base).ok()?.join(url).ok().map(|url| url.to_string())
+}
+
+fn patch_step(manifest: &FileManifest, entry: &FileEntry, from_sha256: &str) -> Option<PatchStep> {
+    let patch = entry.patches.iter().find(|p| p.from_sha256 == from_sha256)?;
+    Some(PatchStep {
+        url: resolve_url(manifest.base_url.as_deref(), &patch.url)?,
+        patch: patch.clone(),
+        to_sha256: entry.sha256.clone(),
+        to_size: entry.size,
+    })
+}
+
+// Follow a file through `versions` (oldest first, ending with the target), taking the patch
+// from whatever content it had before each change. A patch straight from the installed content
+// wins over a chain.
+fn patch_chain(source: &FileEntry, versions: &[&FileManifest]) -> Option<Vec<PatchStep>> {
+    let target_manifest = versions.last()?;
+    let target = target_manifest.files.iter().find(|f| f.path == source.path)?;
+    if let Some(step) = patch_step(target_manifest, target, &source.sha256) {
+        return Some(vec![step]);
+    }
+
+    let mut current = source.sha256.as_str();
+    let mut steps = Vec::new();
+    for manifest in versions {
+        let entry = manifest.files.iter().find(|f| f.path == source.path)?;
+        if entry.sha256 != current {
+            steps.push(patch_step(manifest, entry, current)?);
+            current = &entry.sha256;
+        }
+    }
+    Some(steps)
+}
+
+// Match target files to installed ones by content, so renamed or moved files are reused too.
+// Changed files are patched when the published patches, through the `intermediate` versions
+// between the two builds (oldest first), cost less than the whole file.
+pub fn plan(installed: &FileManifest, intermediate: &[FileManifest], target: &FileManifest) -> DeltaPlan {
+    let mut by_content: HashMap<(&str, u64), &str> = HashMap::new();
+    for entry in &installed.files {
+        by_content.entry((entry.sha256.as_str(), entry.size)).or_insert(entry.path.as_str());
+    }
+    let versions: Vec<&FileManifest> = intermediate.iter().chain(std::iter::once(target)).collect();
+
+    let mut delta = DeltaPlan::default();
+    for entry in &target.files {
+        if let Some(path) = by_content.get(&(entry.sha256.as_str(), entry.size)) {
+            delta.reuse.push((entry.clone(), path.to_string()));
+            continue;
+        }
+        let chain = installed.files.iter()
+            .find(|f| f.path == entry.path)
+            .and_then(|source| patch_chain(source, &versions).map(|steps| PatchChain { source: source.clone(), steps }))
+            .filter(|chain| chain.size() < entry.size);
+        match chain {
+            Some(chain) => delta.patch.push((entry.clone(), chain)),
+            None => delta.download.push(entry.clone()),
+        }
+    }
+    let kept: HashSet<&str> = target.files.iter().map(|f| f.path.as_str()).collect();
+    delta.removed = installed.files.iter()
+        .filter(|f| !kept.contains(f.path.as_str()))
+        .map(|f| f.path.clone())
+        .collect();
+    delta
+}
+
+fn staged_path(dest: &Path, entry: &FileEntry) -> Result<PathBuf, String> {
+    let path = dest.join(enclosed_path(&entry.path).ok_or_else(|| format!("Unsafe path in file manifest: {}", entry.path))?);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    Ok(path)
+}
+
+// Copy an installed file into staging, hashing it on the way. Returns false when the player
+// changed the file, in which case nothing is left behind.
+fn copy_verified(source: &Path, target: &Path, entry: &FileEntry) -> Result<bool, String> {
+    let mut input = match fs::File::open(source) {
+        Ok(file) => file,
+        Err(_) => return Ok(false),
+    };
+    let mut output = fs::File::create(target).map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut total = 0u64;
+    loop {
+        let read = input.read(&mut buffer).map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        output.write_all(&buffer[..read]).map_err(|e| e.to_string())?;
+        total += read as u64;
+    }
+    drop(output);
+
+    if total == entry.size && hex::encode(hasher.finalize()) == entry.sha256 {
+        return Ok(true);
+    }
+    let _ = fs::remove_file(target);
+    Ok(false)
+}
+
+// `base_url` + the entry's path, with each segment percent-encoded
+fn file_url(base_url: &str, entry: &FileEntry) -> Result<reqwest::Url, String> {
+    let mut url = reqwest::Url::parse(base_url).map_err(|e| format!("Invalid files base URL {}: {}", base_url, e))?;
+    url.path_segments_mut()
+        .map_err(|_| format!("Invalid files base URL {}", base_url))?
+        .pop_if_empty()
+        .extend(entry.path.split('/'));
+    Ok(url)
+}
+
+async fn download_file(client: &reqwest::Client, url: reqwest::Url, target: &Path, name: &str, expected: &ExpectedArchive, progress: &mut ProgressReporter, control: &JobControl) -> Result<(), DownloadError> {
+    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Failed to download {}: HTTP {}", name, response.status()).into());
+    }
+    if let Some(total) = response.content_length() {
+        expected.check_size(total)?;
+    }
+
+    let mut file = fs::File::create(target).map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    let mut written = 0u64;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        control.check()?;
+        let chunk = chunk.map_err(|e| e.to_string())?;
+        control.throttle(chunk.len()).await;
+        file.write_all(&chunk).map_err(|e| e.to_string())?;
+        hasher.update(&chunk);
+        written += chunk.len() as u64;
+        progress.advance(chunk.len() as u64);
+    }
+    file.flush().map_err(|e| e.to_string())?;
+    expected.check(hasher, written).map_err(|e| match e {
+        DownloadError::Integrity(reason) => DownloadError::Integrity(format!("{}: {}", name, reason)),
+        other => other,
+    })
+}
+
+// Download each patch of a chain and apply it to the installed file, checking the result of
+// every step. Intermediate files live in `work_dir`; the last step writes `target`.
+async fn apply_chain(client: &reqwest::Client, chain: &PatchChain, source: &Path, work_dir: &Path, target: &Path, progress: &mut ProgressReporter, control: &JobControl) -> Result<(), DownloadError> {
+    let mut current = source.to_path_buf();
+    for (index, step) in chain.steps.iter().enumerate() {
+        let url = reqwest::Url::parse(&step.url).map_err(|e| format!("Invalid patch URL {}: {}", step.url, e))?;
+        let patch_path = work_dir.join(format!("{}.bsdiff", index));
+        let expected = ExpectedArchive { sha256: Some(step.patch.sha256.clone()), size: Some(step.patch.size) };
+        download_file(client, url, &patch_path, &format!("patch for {}", chain.source.path), &expected, progress, control).await?;
+        control.check()?;
+
+        let output = if index + 1 == chain.steps.len() { target.to_path_buf() } else { work_dir.join(format!("{}.out", index)) };
+        let (old, patched) = (current.clone(), output.clone());
+        let (sha256, size) = tokio::task::spawn_blocking(move || bspatch::apply(&old, &patch_path, &patched))
+            .await
+            .map_err(|e| e.to_string())??;
+        if sha256 != step.to_sha256 || size != step.to_size {
+            return Err(DownloadError::Integrity(format!("{}: patched file does not match the manifest", chain.source.path)));
+        }
+        current = output;
+    }
+    Ok(())
+}
+
+// Build the target version in `dest` from the installed files, patches for changed files and
+// the remaining files fetched one by one from `base_url`. Every file is checked against the
+// target manifest; any error means the caller should fall back to the full archive.
+pub async fn apply(delta: &DeltaPlan, installed_dir: &Path, base_url: Option<&str>, dest: &Path, progress: &mut ProgressReporter, control: &JobControl) -> Result<ExtractReport, DownloadError> {
+    let mut report = ExtractReport::default();
+    let mut download: Vec<&FileEntry> = delta.download.iter().collect();
+
+    progress.set_phase(DownloadPhase::Extracting);
+    for (entry, installed_path) in &delta.reuse {
+        control.check()?;
+        let source = installed_dir.join(enclosed_path(installed_path).ok_or("Unsafe path in installed file manifest")?);
+        let target = staged_path(dest, entry)?;
+        let file = entry.clone();
+        let copied = tokio::task::spawn_blocking(move || copy_verified(&source, &target, &file))
+            .await
+            .map_err(|e| e.to_string())??;
+        if copied {
+            report.files_extracted += 1;
+            report.bytes_extracted += entry.size;
+        } else {
+            println!("{} was changed locally, downloading it again", installed_path);
+            download.push(entry);
+        }
+    }
+
+    // Patches only apply to the exact content they were made from
+    let mut patch = Vec::new();
+    for (entry, chain) in &delta.patch {
+        control.check()?;
+        let source = installed_dir.join(enclosed_path(&chain.source.path).ok_or("Unsafe path in installed file manifest")?);
+        let (path, expected) = (source.clone(), chain.source.clone());
+        let unchanged = tokio::task::spawn_blocking(move || files::file_matches(&path, &expected))
+            .await
+            .map_err(|e| e.to_string())??;
+        if unchanged {
+            patch.push((entry, chain, source));
+        } else {
+            println!("{} was changed locally, downloading it instead of patching", chain.source.path);
+            download.push(entry);
+        }
+    }
+
+    progress.set_phase(DownloadPhase::Downloading);
+    let patch_size: u64 = patch.iter().map(|(_, chain, _)| chain.size()).sum();
+    progress.set_total(Some(patch_size + download.iter().map(|f| f.size).sum::<u64>()));
+    progress.set_downloaded(0);
+    let client = reqwest::Client::new();
+
+    if !patch.is_empty() {
+        let work_dir = dest.with_file_name(format!("{}.patches", dest.file_name().unwrap_or_default().to_string_lossy()));
+        fs::create_dir_all(&work_dir).map_err(|e| e.to_string())?;
+        let mut result = Ok(());
+        for (entry, chain, source) in patch {
+            let target = staged_path(dest, entry)?;
+            result = apply_chain(&client, chain, &source, &work_dir, &target, progress, control).await;
+            if result.is_err() {
+                break;
+            }
+            report.files_extracted += 1;
+            report.bytes_extracted += entry.size;
+        }
+        let _ = fs::remove_dir_all(&work_dir);
+        result?;
+    }
+
+    for entry in download {
+        let base_url = base_url.ok_or_else(|| format!("{} can only be installed from the full archive", entry.path))?;
+        let target = staged_path(dest, entry)?;
+        let expected = ExpectedArchive { sha256: Some(entry.sha256.clone()), size: Some(entry.size) };
+        download_file(&client, file_url(base_url, entry)?, &target, &entry.path, &expected, progress, control).await?;
+        report.files_extracted += 1;
+        report.bytes_extracted += entry.size;
+    }
+    Ok(report)
+}
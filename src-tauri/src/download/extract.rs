@@ -0,0 +1,386 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use zip::result::ZipError;
+
+use super::queue::JobControl;
+use super::DownloadError;
+
+// Zip-bomb protection: an archive may expand to at most this many times its own size,
+// but never less than MIN_UNCOMPRESSED_LIMIT nor more than MAX_UNCOMPRESSED_LIMIT
+const MAX_COMPRESSION_RATIO: u64 = 100;
+const MIN_UNCOMPRESSED_LIMIT: u64 = 1024 * 1024 * 1024;
+const MAX_UNCOMPRESSED_LIMIT: u64 = 64 * 1024 * 1024 * 1024;
+
+// File type bits of a unix mode
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtractionMode {
+    // Download the archive to the cache folder, verify it, extract it, then delete it
+    Cached,
+    // Extract entries while the archive downloads; needs no archive on disk but cannot resume
+    Streaming,
+}
+
+impl Default for ExtractionMode {
+    fn default() -> Self {
+        ExtractionMode::Cached
+    }
+}
+
+// Result of extracting from a stream; some archives can only be read from their central directory
+pub enum StreamOutcome {
+    Extracted(ExtractReport),
+    Unsupported(String),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RejectedEntry {
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ExtractReport {
+    pub files_extracted: usize,
+    pub bytes_extracted: u64,
+    // Entries skipped because they were unsafe to write
+    pub rejected_entries: Vec<RejectedEntry>,
+}
+
+impl ExtractReport {
+    fn reject(&mut self, name: &str, reason: &str) {
+        eprintln!("Rejected archive entry {}: {}", name, reason);
+        self.rejected_entries.push(RejectedEntry {
+            name: name.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+}
+
+pub fn uncompressed_limit(archive_size: u64) -> u64 {
+    archive_size
+        .saturating_mul(MAX_COMPRESSION_RATIO)
+        .clamp(MIN_UNCOMPRESSED_LIMIT, MAX_UNCOMPRESSED_LIMIT)
+}
+
+// Relative path for an archive entry name, or None when it is absolute or climbs out
+// with `..`. Both separators are accepted since Windows-made archives mix them.
+pub fn enclosed_path(name: &str) -> Option<PathBuf> {
+    if name.contains('\0') || name.starts_with('/') || name.starts_with('\\') {
+        return None;
+    }
+    let mut path = PathBuf::new();
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => return None,
+            // Drive letters and alternate data streams
+            part if part.contains(':') => return None,
+            part => path.push(part),
+        }
+    }
+    Some(path)
+}
+
+// True when `relative` below `root` passes through a symlink that already exists on disk
+fn crosses_symlink(root: &Path, relative: &Path) -> bool {
+    let mut current = root.to_path_buf();
+    for component in relative.components() {
+        current.push(component);
+        match fs::symlink_metadata(&current) {
+            Ok(metadata) if metadata.file_type().is_symlink() => return true,
+            Ok(_) => {}
+            // Nothing below a missing path can be a link yet
+            Err(_) => return false,
+        }
+    }
+    false
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntryKind {
+    File,
+    Directory,
+    Link,
+    // Devices, fifos and anything else a game never needs
+    Other,
+}
+
+// Write one archive entry below `dest`, or record why it was rejected. Rejected entries are
+// still read to the end since some formats decode entries from one continuous stream.
+fn write_entry(name: &str, kind: EntryKind, declared_size: u64, reader: &mut dyn Read, dest: &Path, limit: u64, report: &mut ExtractReport) -> Result<(), DownloadError> {
+    let rejection = match (enclosed_path(name), kind) {
+        (None, _) => Err("path escapes the install folder"),
+        (_, EntryKind::Link) => Err("links are not allowed"),
+        (_, EntryKind::Other) => Err("unsupported entry type"),
+        (Some(relative), _) if crosses_symlink(dest, &relative) => Err("path goes through a symbolic link"),
+        (Some(relative), _) => Ok(relative),
+    };
+    let relative = match rejection {
+        Ok(relative) => relative,
+        Err(reason) => {
+            report.reject(name, reason);
+            io::copy(&mut reader.take(limit), &mut io::sink()).map_err(|e| e.to_string())?;
+            return Ok(());
+        }
+    };
+
+    let outpath = dest.join(&relative);
+    if kind == EntryKind::Directory {
+        fs::create_dir_all(&outpath).map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    // Check the declared size first, then enforce the limit on what is actually written
+    let remaining = limit - report.bytes_extracted;
+    if declared_size > remaining {
+        return Err(format!("Archive expands beyond {} bytes, refusing to extract {}", limit, name).into());
+    }
+    if let Some(parent) = outpath.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut outfile = fs::File::create(&outpath).map_err(|e| e.to_string())?;
+    let written = io::copy(&mut reader.take(remaining + 1), &mut outfile).map_err(|e| e.to_string())?;
+    if written > remaining {
+        drop(outfile);
+        let _ = fs::remove_file(&outpath);
+        return Err(format!("Archive expands beyond {} bytes, refusing to extract {}", limit, name).into());
+    }
+
+    report.bytes_extracted += written;
+    report.files_extracted += 1;
+    Ok(())
+}
+
+fn zip_entry_kind(entry: &zip::read::ZipFile<'_>) -> EntryKind {
+    if entry.unix_mode().map(|mode| mode & S_IFMT == S_IFLNK).unwrap_or(false) {
+        EntryKind::Link
+    } else if entry.is_dir() {
+        EntryKind::Directory
+    } else {
+        EntryKind::File
+    }
+}
+
+// Extract a zip into `dest`. Entries that would land outside `dest` (absolute paths, `..`,
+// symlinks) are skipped and reported; expanding past the size limit aborts the extraction.
+pub fn extract_zip(zip_path: &Path, dest: &Path, limit: u64, control: &JobControl) -> Result<ExtractReport, DownloadError> {
+    let file = fs::File::open(zip_path).map_err(|e| e.to_string())?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+    let mut report = ExtractReport::default();
+
+    for i in 0..archive.len() {
+        if control.is_cancelled() {
+            return Err(DownloadError::Cancelled);
+        }
+        let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        let name = entry.name().to_string();
+        let kind = zip_entry_kind(&entry);
+        let size = entry.size();
+        write_entry(&name, kind, size, &mut entry, dest, limit, &mut report)?;
+    }
+
+    Ok(report)
+}
+
+// Extract a zip as it is read front to back, using the local file headers. Archives whose
+// entries use data descriptors (sizes only known after the data) are reported as unsupported.
+pub fn extract_zip_stream<R: Read>(reader: &mut R, dest: &Path, limit: u64) -> Result<StreamOutcome, DownloadError> {
+    let mut report = ExtractReport::default();
+    loop {
+        let mut entry = match zip::read::read_zipfile_from_stream(reader) {
+            Ok(Some(entry)) => entry,
+            // Reached the central directory
+            Ok(None) => break,
+            Err(ZipError::UnsupportedArchive(reason)) => return Ok(StreamOutcome::Unsupported(reason.to_string())),
+            Err(e) => return Err(e.to_string().into()),
+        };
+        let name = entry.name().to_string();
+        let kind = zip_entry_kind(&entry);
+        let size = entry.size();
+        write_entry(&name, kind, size, &mut entry, dest, limit, &mut report)?;
+    }
+
+    // Consume the rest so the whole archive still gets hashed
+    io::copy(reader, &mut io::sink()).map_err(|e| e.to_string())?;
+    Ok(StreamOutcome::Extracted(report))
+}
+
+// Extract an already decompressed tar stream
+pub fn extract_tar(reader: &mut dyn Read, dest: &Path, limit: u64, control: &JobControl) -> Result<ExtractReport, DownloadError> {
+    let mut archive = tar::Archive::new(reader);
+    let mut report = ExtractReport::default();
+
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        if control.is_cancelled() {
+            return Err(DownloadError::Cancelled);
+        }
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        let name = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+        let kind = match entry.header().entry_type() {
+            tar::EntryType::Regular | tar::EntryType::Continuous => EntryKind::File,
+            tar::EntryType::Directory => EntryKind::Directory,
+            tar::EntryType::Symlink | tar::EntryType::Link => EntryKind::Link,
+            _ => EntryKind::Other,
+        };
+        let size = entry.size();
+        write_entry(&name, kind, size, &mut entry, dest, limit, &mut report)?;
+    }
+
+    // Consume the padding so the whole archive still gets hashed when streaming
+    io::copy(archive.into_inner(), &mut io::sink()).map_err(|e| e.to_string())?;
+    Ok(report)
+}
+
+#[cfg(feature = "sevenz")]
+pub fn extract_7z(path: &Path, dest: &Path, limit: u64, control: &JobControl) -> Result<ExtractReport, DownloadError> {
+    // Windows attribute flags, plus the unix mode stored in the high bits by p7zip
+    const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+    const FILE_ATTRIBUTE_UNIX_EXTENSION: u32 = 0x8000;
+
+    let mut archive = sevenz_rust::SevenZReader::open(path, sevenz_rust::Password::empty()).map_err(|e| e.to_string())?;
+    let mut report = ExtractReport::default();
+    let mut failure = None;
+
+    archive.for_each_entries(|entry, reader| {
+        if control.is_cancelled() {
+            failure = Some(DownloadError::Cancelled);
+            return Ok(false);
+        }
+        let attributes = if entry.has_windows_attributes { entry.windows_attributes() } else { 0 };
+        let is_link = attributes & FILE_ATTRIBUTE_REPARSE_POINT != 0
+            || (attributes & FILE_ATTRIBUTE_UNIX_EXTENSION != 0 && (attributes >> 16) & S_IFMT == S_IFLNK);
+        let kind = if entry.is_anti_item() {
+            EntryKind::Other
+        } else if is_link {
+            EntryKind::Link
+        } else if entry.is_directory() {
+            EntryKind::Directory
+        } else {
+            EntryKind::File
+        };
+        match write_entry(entry.name(), kind, entry.size(), reader, dest, limit, &mut report) {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                failure = Some(e);
+                Ok(false)
+            }
+        }
+    }).map_err(|e| e.to_string())?;
+
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(report),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Empty scratch folder per test, removed when dropped
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Scratch {
+            let dir = std::env::temp_dir().join(format!("extract-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(dir.join("dest")).unwrap();
+            Scratch(dir)
+        }
+
+        fn dest(&self) -> PathBuf {
+            self.0.join("dest")
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn write(name: &str, kind: EntryKind, declared_size: u64, data: &[u8], dest: &Path, limit: u64, report: &mut ExtractReport) -> Result<(), DownloadError> {
+        write_entry(name, kind, declared_size, &mut &data[..], dest, limit, report)
+    }
+
+    #[test]
+    fn enclosed_path_rejects_escaping_names() {
+        for name in ["../evil.exe", "game/../../evil.exe", "..\\evil.exe", "/etc/passwd", "\\Windows\\evil.exe", "C:evil.exe", "C:\\Windows\\evil.exe", "game.exe:stream", "a\0b"] {
+            assert!(enclosed_path(name).is_none(), "{}", name);
+        }
+        assert_eq!(enclosed_path("game\\data/./level1.pak"), Some(["game", "data", "level1.pak"].iter().collect()));
+    }
+
+    #[test]
+    fn unsafe_entries_are_skipped() {
+        let scratch = Scratch::new("unsafe");
+        let mut report = ExtractReport::default();
+        for name in ["../outside.txt", "C:outside.txt", "data.txt:ads"] {
+            write(name, EntryKind::File, 4, b"evil", &scratch.dest(), 1024, &mut report).unwrap();
+        }
+        write("link", EntryKind::Link, 0, b"../outside.txt", &scratch.dest(), 1024, &mut report).unwrap();
+        write("ok.txt", EntryKind::File, 2, b"ok", &scratch.dest(), 1024, &mut report).unwrap();
+
+        assert_eq!(report.rejected_entries.len(), 4);
+        assert_eq!(report.files_extracted, 1);
+        assert!(!scratch.0.join("outside.txt").exists());
+        assert!(fs::symlink_metadata(scratch.dest().join("link")).is_err());
+        assert_eq!(fs::read(scratch.dest().join("ok.txt")).unwrap(), b"ok");
+    }
+
+    #[test]
+    fn tar_symlinks_are_skipped() {
+        let scratch = Scratch::new("tar-link");
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_link_name("../../outside").unwrap();
+        builder.append_data(&mut header, "saves", io::empty()).unwrap();
+        let tar = builder.into_inner().unwrap();
+
+        let report = extract_tar(&mut &tar[..], &scratch.dest(), 1024, &JobControl::default()).unwrap();
+        assert_eq!(report.rejected_entries[0].reason, "links are not allowed");
+        assert!(fs::symlink_metadata(scratch.dest().join("saves")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_parent_is_not_followed() {
+        let scratch = Scratch::new("parent-link");
+        fs::create_dir_all(scratch.0.join("outside")).unwrap();
+        std::os::unix::fs::symlink(scratch.0.join("outside"), scratch.dest().join("saves")).unwrap();
+        let mut report = ExtractReport::default();
+        write("saves/evil.txt", EntryKind::File, 4, b"evil", &scratch.dest(), 1024, &mut report).unwrap();
+
+        assert_eq!(report.rejected_entries[0].reason, "path goes through a symbolic link");
+        assert!(!scratch.0.join("outside/evil.txt").exists());
+    }
+
+    #[test]
+    fn declared_size_over_limit_is_refused() {
+        let scratch = Scratch::new("declared");
+        let mut report = ExtractReport::default();
+        write("a.bin", EntryKind::File, 6, b"123456", &scratch.dest(), 10, &mut report).unwrap();
+        let error = write("b.bin", EntryKind::File, 6, b"123456", &scratch.dest(), 10, &mut report).unwrap_err();
+        assert!(error.to_string().contains("expands beyond 10 bytes"), "{}", error);
+        assert!(!scratch.dest().join("b.bin").exists());
+    }
+
+    #[test]
+    fn bytes_over_limit_are_refused() {
+        let scratch = Scratch::new("actual");
+        let mut report = ExtractReport::default();
+        // Declares less than it holds, as a crafted archive would
+        let error = write("bomb.bin", EntryKind::File, 1, &[0u8; 64], &scratch.dest(), 16, &mut report).unwrap_err();
+        assert!(error.to_string().contains("expands beyond 16 bytes"), "{}", error);
+        assert!(!scratch.dest().join("bomb.bin").exists());
+        assert_eq!(report.bytes_extracted, 0);
+    }
+}
@@ -0,0 +1,80 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use sha2::{Digest, Sha256};
+
+use super::DownloadError;
+use crate::GameInfo;
+
+// What the manifest promises about an archive; either part may be missing
+#[derive(Debug, Clone, Default)]
+pub struct ExpectedArchive {
+    pub sha256: Option<String>,
+    pub size: Option<u64>,
+}
+
+impl ExpectedArchive {
+    pub fn for_game(game: &GameInfo) -> Self {
+        ExpectedArchive {
+            sha256: game.sha256.as_ref().map(|hash| hash.trim().to_ascii_lowercase()),
+            size: game.size,
+        }
+    }
+
+    pub fn check_size(&self, size: u64) -> Result<(), DownloadError> {
+        match self.size {
+            Some(expected) if expected != size => Err(DownloadError::Integrity(format!(
+                "expected {} bytes, got {}", expected, size
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    // Compare a finished hash and byte count against the manifest
+    pub fn check(&self, hasher: Sha256, size: u64) -> Result<(), DownloadError> {
+        self.check_size(size)?;
+        if let Some(expected) = &self.sha256 {
+            let actual = hex::encode(hasher.finalize());
+            if actual != *expected {
+                return Err(DownloadError::Integrity(format!(
+                    "SHA-256 mismatch: expected {}, got {}", expected, actual
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Feed a file into the hasher, e.g. the part already on disk when resuming
+pub fn hash_file_into(path: &Path, hasher: &mut Sha256) -> Result<u64, String> {
+    let mut file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut total = 0u64;
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        total += read as u64;
+    }
+    Ok(total)
+}
+
+pub fn verify_file(path: &Path, expected: &ExpectedArchive) -> Result<(), DownloadError> {
+    if expected.sha256.is_none() {
+        let size = fs::metadata(path).map_err(|e| e.to_string())?.len();
+        return expected.check_size(size);
+    }
+    let mut hasher = Sha256::new();
+    let size = hash_file_into(path, &mut hasher)?;
+    expected.check(hasher, size)
+}
+
+// verify_file on a blocking thread; archives are gigabytes and hashing them would stall the runtime
+pub async fn verify_file_blocking(path: &Path, expected: &ExpectedArchive) -> Result<(), DownloadError> {
+    let (path, expected) = (path.to_path_buf(), expected.clone());
+    tokio::task::spawn_blocking(move || verify_file(&path, &expected))
+        .await
+        .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,48 @@
+pub mod archive;
+pub mod bspatch;
+pub mod delta;
+pub mod extract;
+pub mod integrity;
+pub mod progress;
+pub mod queue;
+pub mod resume;
+pub mod segmented;
+pub mod space;
+pub mod staging;
+pub mod stream;
+pub mod throttle;
+
+use std::fmt;
+
+// Why a download stopped. Paused and Cancelled are requested through the queue.
+#[derive(Debug, Clone)]
+pub enum DownloadError {
+    Paused,
+    Cancelled,
+    // The archive does not match the manifest's sha256 or size
+    Integrity(String),
+    Failed(String),
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownloadError::Paused => write!(f, "Download paused"),
+            DownloadError::Cancelled => write!(f, "Download cancelled"),
+            DownloadError::Integrity(e) => write!(f, "Integrity check failed: {}", e),
+            DownloadError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<String> for DownloadError {
+    fn from(e: String) -> Self {
+        DownloadError::Failed(e)
+    }
+}
+
+impl From<&str> for DownloadError {
+    fn from(e: &str) -> Self {
+        DownloadError::Failed(e.to_string())
+    }
+}
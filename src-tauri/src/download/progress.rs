@@ -0,0 +1,153 @@
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+pub const DOWNLOAD_PROGRESS_EVENT: &str = "download-progress";
+
+// At most this many progress events per second per download
+const EMIT_INTERVAL: Duration = Duration::from_millis(250);
+
+// Weight of the newest sample in the smoothed speed
+const SPEED_SMOOTHING: f64 = 0.3;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadPhase {
+    // Queued outside the download window; nothing runs until it opens
+    Waiting,
+    Downloading,
+    Verifying,
+    Extracting,
+    Finalizing,
+    Completed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DownloadProgress {
+    pub game_id: String,
+    pub phase: DownloadPhase,
+    pub bytes_downloaded: u64,
+    pub total_bytes: Option<u64>,
+    pub percent: Option<f64>,
+    pub speed_bytes_per_sec: f64,
+    pub eta_seconds: Option<u64>,
+    // While waiting: when the download window opens ("HH:MM", local time)
+    pub window_start: Option<String>,
+}
+
+// Tell the frontend a queued download is held until the download window opens
+pub fn emit_waiting(app: &AppHandle, game_id: &str, window_start: &str) {
+    let progress = DownloadProgress {
+        game_id: game_id.to_string(),
+        phase: DownloadPhase::Waiting,
+        bytes_downloaded: 0,
+        total_bytes: None,
+        percent: None,
+        speed_bytes_per_sec: 0.0,
+        eta_seconds: None,
+        window_start: Some(window_start.to_string()),
+    };
+    if let Err(e) = app.emit_all(DOWNLOAD_PROGRESS_EVENT, progress) {
+        eprintln!("Failed to emit download progress: {}", e);
+    }
+}
+
+// Tracks one download and emits throttled progress events to the frontend
+pub struct ProgressReporter {
+    app: AppHandle,
+    game_id: String,
+    phase: DownloadPhase,
+    downloaded: u64,
+    total: Option<u64>,
+    speed: f64,
+    last_sample: (Instant, u64),
+    last_emit: Option<Instant>,
+}
+
+impl ProgressReporter {
+    pub fn new(app: AppHandle, game_id: &str) -> Self {
+        ProgressReporter {
+            app,
+            game_id: game_id.to_string(),
+            phase: DownloadPhase::Downloading,
+            downloaded: 0,
+            total: None,
+            speed: 0.0,
+            last_sample: (Instant::now(), 0),
+            last_emit: None,
+        }
+    }
+
+    pub fn set_total(&mut self, total: Option<u64>) {
+        self.total = total.filter(|t| *t > 0);
+    }
+
+    // Bytes already on disk before this session, e.g. when resuming
+    pub fn set_downloaded(&mut self, downloaded: u64) {
+        self.downloaded = downloaded;
+        self.last_sample = (Instant::now(), downloaded);
+    }
+
+    pub fn advance(&mut self, bytes: u64) {
+        self.downloaded += bytes;
+        let due = self.last_emit.map(|at| at.elapsed() >= EMIT_INTERVAL).unwrap_or(true);
+        if due {
+            self.emit();
+        }
+    }
+
+    // Phase changes are always emitted so the UI never misses one
+    pub fn set_phase(&mut self, phase: DownloadPhase) {
+        if self.phase != phase {
+            self.phase = phase;
+            self.emit();
+        }
+    }
+
+    fn update_speed(&mut self) {
+        let (at, bytes) = self.last_sample;
+        let elapsed = at.elapsed().as_secs_f64();
+        if elapsed <= 0.0 {
+            return;
+        }
+
+        let sample = self.downloaded.saturating_sub(bytes) as f64 / elapsed;
+        self.speed = if self.speed == 0.0 {
+            sample
+        } else {
+            SPEED_SMOOTHING * sample + (1.0 - SPEED_SMOOTHING) * self.speed
+        };
+        self.last_sample = (Instant::now(), self.downloaded);
+    }
+
+    pub fn snapshot(&self) -> DownloadProgress {
+        let downloading = self.phase == DownloadPhase::Downloading;
+        let eta_seconds = match self.total {
+            Some(total) if downloading && self.speed > 0.0 => {
+                Some((total.saturating_sub(self.downloaded) as f64 / self.speed).ceil() as u64)
+            }
+            _ => None,
+        };
+
+        DownloadProgress {
+            game_id: self.game_id.clone(),
+            phase: self.phase,
+            bytes_downloaded: self.downloaded,
+            total_bytes: self.total,
+            percent: self.total.map(|total| (self.downloaded as f64 / total as f64 * 100.0).min(100.0)),
+            speed_bytes_per_sec: if downloading { self.speed } else { 0.0 },
+            eta_seconds,
+            window_start: None,
+        }
+    }
+
+    fn emit(&mut self) {
+        if self.phase == DownloadPhase::Downloading {
+            self.update_speed();
+        }
+        self.last_emit = Some(Instant::now());
+        if let Err(e) = self.app.emit_all(DOWNLOAD_PROGRESS_EVENT, self.snapshot()) {
+            eprintln!("Failed to emit download progress: {}", e);
+        }
+    }
+}
@@ -0,0 +1,338 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tokio::sync::oneshot;
+
+use super::progress;
+use super::resume::discard_partial;
+use super::throttle::RateLimiter;
+use super::DownloadError;
+use crate::settings::{load_settings, LauncherSettings};
+use crate::InstallResult;
+
+// Emitted with the full job list whenever a job is added or changes state
+pub const DOWNLOADS_CHANGED_EVENT: &str = "downloads-changed";
+// How often queued jobs are reconsidered while waiting for the download window
+const WINDOW_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Active,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_finished(self) -> bool {
+        matches!(self, JobState::Completed | JobState::Failed | JobState::Cancelled)
+    }
+}
+
+const SIGNAL_RUN: u8 = 0;
+const SIGNAL_PAUSE: u8 = 1;
+const SIGNAL_CANCEL: u8 = 2;
+
+// Shared between the queue and a running download, which polls it between chunks
+#[derive(Default)]
+pub struct JobControl {
+    signal: AtomicU8,
+    // Files and folders created by the download, removed when the job is cancelled
+    created: Mutex<Vec<PathBuf>>,
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl JobControl {
+    pub fn check(&self) -> Result<(), DownloadError> {
+        match self.signal.load(Ordering::SeqCst) {
+            SIGNAL_PAUSE => Err(DownloadError::Paused),
+            SIGNAL_CANCEL => Err(DownloadError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    // Wait as needed to keep all downloads under the bandwidth limit
+    pub async fn throttle(&self, bytes: usize) {
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire(bytes as u64).await;
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.signal.load(Ordering::SeqCst) == SIGNAL_CANCEL
+    }
+
+    pub fn track(&self, path: PathBuf) {
+        let mut created = self.created.lock().unwrap_or_else(|e| e.into_inner());
+        if !created.contains(&path) {
+            created.push(path);
+        }
+    }
+
+    fn cleanup(&self) {
+        let mut created = self.created.lock().unwrap_or_else(|e| e.into_inner());
+        for path in created.drain(..) {
+            if path.is_dir() {
+                if let Err(e) = std::fs::remove_dir_all(&path) {
+                    eprintln!("Failed to remove {:?}: {}", path, e);
+                }
+            } else {
+                discard_partial(&path);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DownloadJobInfo {
+    pub id: u64,
+    pub game_id: String,
+    pub download_url: String,
+    pub state: JobState,
+    pub error: Option<String>,
+    // Set once completed
+    pub result: Option<InstallResult>,
+}
+
+struct DownloadJob {
+    info: DownloadJobInfo,
+    control: Arc<JobControl>,
+    // A task is still running for this job (it may already be marked paused or cancelled)
+    running: bool,
+    waiters: Vec<oneshot::Sender<Result<InstallResult, String>>>,
+}
+
+impl DownloadJob {
+    fn notify(&mut self, result: Result<InstallResult, String>) {
+        for waiter in self.waiters.drain(..) {
+            let _ = waiter.send(result.clone());
+        }
+    }
+}
+
+// Queue of game downloads; at most `max_concurrent_downloads` run at once, in queue order,
+// and only inside the download window when one is set
+pub struct DownloadManager {
+    next_id: AtomicU64,
+    jobs: Mutex<Vec<DownloadJob>>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl DownloadManager {
+    pub fn new() -> Self {
+        DownloadManager {
+            next_id: AtomicU64::new(1),
+            jobs: Mutex::new(Vec::new()),
+            limiter: Arc::new(RateLimiter::new()),
+        }
+    }
+
+    // Apply the bandwidth limit and download window from the settings to running and future jobs
+    pub fn apply_limits(&self, settings: &LauncherSettings) {
+        self.limiter.configure(settings.download_speed_limit_kbps * 1024, settings.download_window.clone());
+    }
+
+    fn jobs(&self) -> MutexGuard<'_, Vec<DownloadJob>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn list(&self) -> Vec<DownloadJobInfo> {
+        self.jobs().iter().map(|job| job.info.clone()).collect()
+    }
+
+    fn emit_changed(&self, app: &AppHandle) {
+        if let Err(e) = app.emit_all(DOWNLOADS_CHANGED_EVENT, self.list()) {
+            eprintln!("Failed to emit {}: {}", DOWNLOADS_CHANGED_EVENT, e);
+        }
+    }
+
+    // Add a download to the back of the queue; the receiver resolves once it is installed
+    pub fn enqueue(&self, app: &AppHandle, game_id: &str, download_url: &str) -> Result<(u64, oneshot::Receiver<Result<InstallResult, String>>), String> {
+        let (sender, receiver) = oneshot::channel();
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        {
+            let mut jobs = self.jobs();
+            if jobs.iter().any(|job| job.info.game_id == game_id && !job.info.state.is_finished()) {
+                return Err(format!("{} is already in the download queue", game_id));
+            }
+            jobs.retain(|job| job.info.game_id != game_id);
+            jobs.push(DownloadJob {
+                info: DownloadJobInfo {
+                    id,
+                    game_id: game_id.to_string(),
+                    download_url: download_url.to_string(),
+                    state: JobState::Queued,
+                    error: None,
+                    result: None,
+                },
+                control: Arc::new(JobControl {
+                    limiter: Some(self.limiter.clone()),
+                    ..JobControl::default()
+                }),
+                running: false,
+                waiters: vec![sender],
+            });
+        }
+        self.schedule(app);
+        Ok((id, receiver))
+    }
+
+    pub fn pause(&self, app: &AppHandle, id: u64) -> Result<(), String> {
+        {
+            let mut jobs = self.jobs();
+            let job = jobs.iter_mut().find(|job| job.info.id == id).ok_or("Download not found")?;
+            match job.info.state {
+                JobState::Queued | JobState::Active => {
+                    job.control.signal.store(SIGNAL_PAUSE, Ordering::SeqCst);
+                    job.info.state = JobState::Paused;
+                }
+                _ => return Err("Download is not queued or running".to_string()),
+            }
+        }
+        self.schedule(app);
+        Ok(())
+    }
+
+    pub fn resume(&self, app: &AppHandle, id: u64) -> Result<(), String> {
+        {
+            let mut jobs = self.jobs();
+            let job = jobs.iter_mut().find(|job| job.info.id == id).ok_or("Download not found")?;
+            if job.info.state != JobState::Paused {
+                return Err("Download is not paused".to_string());
+            }
+            job.control.signal.store(SIGNAL_RUN, Ordering::SeqCst);
+            // A task that has not noticed the pause yet simply carries on
+            job.info.state = if job.running { JobState::Active } else { JobState::Queued };
+        }
+        self.schedule(app);
+        Ok(())
+    }
+
+    pub fn cancel(&self, app: &AppHandle, id: u64) -> Result<(), String> {
+        {
+            let mut jobs = self.jobs();
+            let job = jobs.iter_mut().find(|job| job.info.id == id).ok_or("Download not found")?;
+            if job.info.state.is_finished() {
+                return Err("Download has already finished".to_string());
+            }
+            job.control.signal.store(SIGNAL_CANCEL, Ordering::SeqCst);
+            job.info.state = JobState::Cancelled;
+            // A running task cleans up in `finish` once it notices the signal
+            if !job.running {
+                job.control.cleanup();
+                job.notify(Err(DownloadError::Cancelled.to_string()));
+            }
+        }
+        self.schedule(app);
+        Ok(())
+    }
+
+    // Move a job to `position` in the queue; earlier jobs start first
+    pub fn move_to(&self, app: &AppHandle, id: u64, position: usize) -> Result<(), String> {
+        {
+            let mut jobs = self.jobs();
+            let index = jobs.iter().position(|job| job.info.id == id).ok_or("Download not found")?;
+            let job = jobs.remove(index);
+            let position = position.min(jobs.len());
+            jobs.insert(position, job);
+        }
+        self.schedule(app);
+        Ok(())
+    }
+
+    fn finish(&self, app: &AppHandle, id: u64, result: Result<InstallResult, DownloadError>) {
+        {
+            let mut jobs = self.jobs();
+            if let Some(job) = jobs.iter_mut().find(|job| job.info.id == id) {
+                job.running = false;
+                match result {
+                    Ok(installed) => {
+                        job.info.state = JobState::Completed;
+                        job.info.result = Some(installed.clone());
+                        job.notify(Ok(installed));
+                    }
+                    Err(_) if job.control.is_cancelled() => {
+                        job.info.state = JobState::Cancelled;
+                        job.control.cleanup();
+                        job.notify(Err(DownloadError::Cancelled.to_string()));
+                    }
+                    // Partial files are kept; requeue if it was resumed before the task stopped
+                    Err(DownloadError::Paused) => {
+                        if job.info.state == JobState::Active {
+                            job.info.state = JobState::Queued;
+                        }
+                    }
+                    Err(e) => {
+                        println!("Download of {} failed: {}", job.info.game_id, e);
+                        job.info.state = JobState::Failed;
+                        job.info.error = Some(e.to_string());
+                        job.notify(Err(e.to_string()));
+                    }
+                }
+            }
+        }
+        self.schedule(app);
+    }
+
+    // Start queued jobs in order while there are free slots. Outside the download window queued
+    // jobs wait, and say so through a progress event; jobs already running carry on.
+    pub fn schedule(&self, app: &AppHandle) {
+        let settings = load_settings(app);
+        let limit = settings.max_concurrent_downloads.max(1);
+        let closed_window = settings.download_window.as_ref().filter(|w| !w.is_open_now());
+        let mut to_start = Vec::new();
+        if let Some(window) = closed_window {
+            let waiting: Vec<String> = self.jobs().iter()
+                .filter(|job| job.info.state == JobState::Queued && !job.running)
+                .map(|job| job.info.game_id.clone())
+                .collect();
+            for game_id in waiting {
+                progress::emit_waiting(app, &game_id, &window.start);
+            }
+        } else {
+            let mut jobs = self.jobs();
+            let mut running = jobs.iter().filter(|job| job.running).count();
+            for job in jobs.iter_mut() {
+                if running >= limit {
+                    break;
+                }
+                if job.info.state == JobState::Queued && !job.running {
+                    job.running = true;
+                    job.info.state = JobState::Active;
+                    job.info.error = None;
+                    running += 1;
+                    to_start.push((job.info.id, job.info.game_id.clone(), job.control.clone()));
+                }
+            }
+        }
+
+        for (id, game_id, control) in to_start {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let result = crate::install_game(app.clone(), &game_id, &control).await;
+                app.state::<DownloadManager>().finish(&app, id, result);
+            });
+        }
+        self.emit_changed(app);
+    }
+
+    // Periodically start jobs that were waiting for the download window to open
+    pub fn start_window_timer(app: AppHandle) {
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::time::sleep(WINDOW_CHECK_INTERVAL).await;
+                let manager = app.state::<DownloadManager>();
+                let waiting = manager.jobs().iter().any(|job| job.info.state == JobState::Queued && !job.running);
+                if waiting {
+                    manager.schedule(&app);
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,222 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use sha2::{Digest, Sha256};
+
+use super::integrity::{self, ExpectedArchive};
+use super::progress::{DownloadPhase, ProgressReporter};
+use super::queue::JobControl;
+use super::segmented;
+use super::DownloadError;
+
+// Sidecar written next to a partial download so a later attempt can pick it up
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PartialDownload {
+    pub url: String,
+    pub etag: Option<String>,
+    pub total_bytes: Option<u64>,
+    pub accepts_ranges: bool,
+}
+
+pub fn state_path(part_path: &Path) -> PathBuf {
+    let mut name = part_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".json");
+    part_path.with_file_name(name)
+}
+
+fn load_state(part_path: &Path) -> Option<PartialDownload> {
+    let contents = fs::read_to_string(state_path(part_path)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn save_state(part_path: &Path, state: &PartialDownload) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+    fs::write(state_path(part_path), json).map_err(|e| e.to_string())
+}
+
+// Remove a partial download and its sidecars
+pub fn discard_partial(part_path: &Path) {
+    let _ = fs::remove_file(part_path);
+    let _ = fs::remove_file(state_path(part_path));
+    let _ = fs::remove_file(segmented::state_path(part_path));
+}
+
+// Only a strong ETag can validate a range request (If-Range)
+fn resumable_etag(state: &PartialDownload) -> Option<&str> {
+    state.etag.as_deref().filter(|etag| !etag.starts_with("W/"))
+}
+
+pub fn header_value(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    response.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
+// Start offset and total size from "Content-Range: bytes 100-999/1000"
+pub fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let range = value.trim().strip_prefix("bytes ")?;
+    let (span, total) = range.split_once('/')?;
+    let start = span.split_once('-')?.0.trim().parse().ok()?;
+    Some((start, total.trim().parse().ok()))
+}
+
+type Transfer = (reqwest::Response, fs::File, u64, Option<u64>);
+
+// Send the request and open the part file. With `resume` = (offset, known total, etag) a
+// range is requested; None is returned when the server will not continue that range.
+async fn start_transfer(url: &str, part_path: &Path, resume: Option<(u64, Option<u64>, &str)>) -> Result<Option<Transfer>, String> {
+    let mut request = reqwest::Client::new().get(url);
+    if let Some((offset, _, etag)) = resume {
+        println!("Resuming download of {} at byte {}", url, offset);
+        request = request
+            .header(reqwest::header::RANGE, format!("bytes={}-", offset))
+            .header(reqwest::header::IF_RANGE, etag);
+    }
+
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+
+    if let Some((offset, known_total, _)) = resume {
+        if status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            return Ok(None);
+        }
+        if status == reqwest::StatusCode::PARTIAL_CONTENT {
+            let content_range = header_value(&response, reqwest::header::CONTENT_RANGE)
+                .and_then(|v| parse_content_range(&v));
+            return match content_range {
+                Some((start, total)) if start == offset => {
+                    let file = fs::OpenOptions::new().append(true).open(part_path).map_err(|e| e.to_string())?;
+                    Ok(Some((response, file, offset, total.or(known_total))))
+                }
+                // Server answered a different range than asked for
+                _ => Ok(None),
+            };
+        }
+        if status.is_success() {
+            // Server ignored the range (e.g. the file changed), it is sending everything again
+            println!("Server ignored the range request, restarting download of {}", url);
+        }
+    }
+
+    if !status.is_success() {
+        return Err(format!("Download failed: HTTP {}", status));
+    }
+    let file = fs::File::create(part_path).map_err(|e| e.to_string())?;
+    let total = response.content_length();
+    Ok(Some((response, file, 0, total)))
+}
+
+// Download `url` into `part_path`, continuing a previous partial download when the
+// server supports ranges and the ETag still matches. The archive is hashed while it streams
+// and checked against `expected`. The sidecar is removed on success; on failure or pause both
+// files are kept for the next attempt, except after an integrity error.
+pub async fn download_resumable(url: &str, part_path: &Path, expected: &ExpectedArchive, progress: &mut ProgressReporter, control: &JobControl) -> Result<(), DownloadError> {
+    progress.set_phase(DownloadPhase::Downloading);
+    if let Some(parent) = part_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    // Writing a single stream invalidates any segment bookkeeping for this file
+    let _ = fs::remove_file(segmented::state_path(part_path));
+    let on_disk = fs::metadata(part_path).map(|m| m.len()).unwrap_or(0);
+    let previous = load_state(part_path).filter(|state| state.url == url);
+    let resume = previous.as_ref()
+        .filter(|state| state.accepts_ranges && on_disk > 0)
+        .and_then(|state| resumable_etag(state).map(|etag| (state, etag.to_string())));
+
+    // Nothing left to fetch from an earlier attempt that finished writing
+    if let Some((state, _)) = &resume {
+        if state.total_bytes == Some(on_disk) {
+            progress.set_phase(DownloadPhase::Verifying);
+            if let Err(e) = integrity::verify_file_blocking(part_path, expected).await {
+                discard_partial(part_path);
+                return Err(e);
+            }
+            fs::remove_file(state_path(part_path)).ok();
+            return Ok(());
+        }
+    }
+
+    let resumed = match &resume {
+        Some((state, etag)) => start_transfer(url, part_path, Some((on_disk, state.total_bytes, etag.as_str()))).await?,
+        None => None,
+    };
+    let (response, file, offset, total) = match resumed {
+        Some(transfer) => transfer,
+        None => {
+            if resume.is_some() {
+                println!("Cannot resume download of {}, starting over", url);
+                discard_partial(part_path);
+            }
+            start_transfer(url, part_path, None)
+                .await?
+                .ok_or("Download could not be started")?
+        }
+    };
+    let mut file = file;
+    let status = response.status();
+
+    // Do not spend bandwidth on a file that cannot match the manifest
+    if let Some(total) = total {
+        if let Err(e) = expected.check_size(total) {
+            discard_partial(part_path);
+            return Err(e);
+        }
+    }
+
+    // A resumed download has to hash the bytes from the earlier attempt first
+    let mut hasher = Sha256::new();
+    if offset > 0 && expected.sha256.is_some() {
+        let hashed = integrity::hash_file_into(part_path, &mut hasher)?;
+        if hashed != offset {
+            discard_partial(part_path);
+            return Err(DownloadError::Integrity(format!("partial download changed on disk ({} bytes, expected {})", hashed, offset)));
+        }
+    }
+
+    let accepts_ranges = header_value(&response, reqwest::header::ACCEPT_RANGES)
+        .map(|v| v.eq_ignore_ascii_case("bytes"))
+        .unwrap_or(status == reqwest::StatusCode::PARTIAL_CONTENT);
+    let state = PartialDownload {
+        url: url.to_string(),
+        etag: header_value(&response, reqwest::header::ETAG)
+            .or_else(|| resume.as_ref().filter(|_| offset > 0).map(|(_, etag)| etag.clone())),
+        total_bytes: total,
+        accepts_ranges,
+    };
+    save_state(part_path, &state)?;
+
+    progress.set_total(total.or(expected.size));
+    progress.set_downloaded(offset);
+
+    let mut written = offset;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        control.check()?;
+        let chunk = chunk.map_err(|e| e.to_string())?;
+        control.throttle(chunk.len()).await;
+        file.write_all(&chunk).map_err(|e| e.to_string())?;
+        hasher.update(&chunk);
+        written += chunk.len() as u64;
+        progress.advance(chunk.len() as u64);
+    }
+    file.flush().map_err(|e| e.to_string())?;
+
+    if let Some(total) = total {
+        if written != total {
+            return Err(format!("Download incomplete: got {} of {} bytes", written, total).into());
+        }
+    }
+
+    progress.set_phase(DownloadPhase::Verifying);
+    if let Err(e) = expected.check(hasher, written) {
+        discard_partial(part_path);
+        return Err(e);
+    }
+
+    fs::remove_file(state_path(part_path)).ok();
+    Ok(())
+}
@@ -0,0 +1,222 @@
+use std::cmp;
+use std::fs;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use super::integrity::{self, ExpectedArchive};
+use super::progress::{DownloadPhase, ProgressReporter};
+use super::queue::JobControl;
+use super::resume::{self, discard_partial, header_value, parse_content_range};
+use super::DownloadError;
+
+// Files smaller than this are not worth splitting, and no segment gets smaller than this
+const MIN_SEGMENT_SIZE: u64 = 8 * 1024 * 1024;
+// How often segment progress is written to the sidecar while downloading
+const SAVE_INTERVAL: Duration = Duration::from_secs(2);
+
+// One byte range of the file, `end` inclusive
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Segment {
+    pub start: u64,
+    pub end: u64,
+    pub written: u64,
+}
+
+impl Segment {
+    fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    fn is_done(&self) -> bool {
+        self.written >= self.len()
+    }
+}
+
+// Sidecar for a segmented download, separate from the single-stream one
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SegmentedDownload {
+    pub url: String,
+    pub etag: Option<String>,
+    pub total_bytes: u64,
+    pub segments: Vec<Segment>,
+}
+
+impl SegmentedDownload {
+    fn written(&self) -> u64 {
+        self.segments.iter().map(|s| s.written).sum()
+    }
+}
+
+pub fn state_path(part_path: &Path) -> PathBuf {
+    let mut name = part_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".segments.json");
+    part_path.with_file_name(name)
+}
+
+fn load_state(part_path: &Path) -> Option<SegmentedDownload> {
+    let contents = fs::read_to_string(state_path(part_path)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn save_state(part_path: &Path, state: &SegmentedDownload) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+    fs::write(state_path(part_path), json).map_err(|e| e.to_string())
+}
+
+fn plan_segments(total: u64, count: usize) -> Vec<Segment> {
+    let count = cmp::max(1, cmp::min(count as u64, total / MIN_SEGMENT_SIZE));
+    let size = (total + count - 1) / count;
+    (0..count)
+        .map(|i| i * size)
+        .take_while(|start| *start < total)
+        .map(|start| Segment { start, end: cmp::min(start + size, total) - 1, written: 0 })
+        .collect()
+}
+
+// Total size and ETag when the server answers a one-byte range request with 206
+async fn probe_ranges(client: &reqwest::Client, url: &str) -> Result<Option<(u64, Option<String>)>, String> {
+    let response = client.get(url)
+        .header(reqwest::header::RANGE, "bytes=0-0")
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        return Ok(None);
+    }
+    let total = header_value(&response, reqwest::header::CONTENT_RANGE)
+        .and_then(|v| parse_content_range(&v))
+        .and_then(|(_, total)| total);
+    let etag = header_value(&response, reqwest::header::ETAG);
+    Ok(total.map(|total| (total, etag)))
+}
+
+// Download `url` into `part_path` using up to `segment_count` concurrent range requests, then
+// verify the reassembled file. Falls back to a single resumable stream for small files and for
+// servers that do not honor ranges. Segment progress survives pauses and restarts.
+pub async fn download_segmented(url: &str, part_path: &Path, segment_count: usize, expected: &ExpectedArchive, progress: &mut ProgressReporter, control: &JobControl) -> Result<(), DownloadError> {
+    if segment_count <= 1 {
+        return resume::download_resumable(url, part_path, expected, progress, control).await;
+    }
+    progress.set_phase(DownloadPhase::Downloading);
+    if let Some(parent) = part_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let client = reqwest::Client::new();
+    let on_disk = fs::metadata(part_path).map(|m| m.len()).ok();
+    // A saved state can only be trusted with a strong ETag to validate the remaining ranges
+    let previous = load_state(part_path).filter(|state| {
+        state.url == url
+            && on_disk == Some(state.total_bytes)
+            && state.etag.as_deref().map(|etag| !etag.starts_with("W/")).unwrap_or(false)
+    });
+
+    let mut state = match previous {
+        Some(state) => {
+            println!("Resuming segmented download of {} ({} of {} bytes)", url, state.written(), state.total_bytes);
+            state
+        }
+        None => {
+            // Keep going with a single-stream partial from an earlier attempt instead of dropping it
+            if on_disk.unwrap_or(0) > 0 && resume::state_path(part_path).exists() {
+                return resume::download_resumable(url, part_path, expected, progress, control).await;
+            }
+            let (total, etag) = match probe_ranges(&client, url).await? {
+                Some((total, etag)) if total >= 2 * MIN_SEGMENT_SIZE => (total, etag),
+                _ => return resume::download_resumable(url, part_path, expected, progress, control).await,
+            };
+            if let Err(e) = expected.check_size(total) {
+                discard_partial(part_path);
+                return Err(e);
+            }
+            discard_partial(part_path);
+            let file = fs::File::create(part_path).map_err(|e| e.to_string())?;
+            file.set_len(total).map_err(|e| e.to_string())?;
+            SegmentedDownload {
+                url: url.to_string(),
+                etag,
+                total_bytes: total,
+                segments: plan_segments(total, segment_count),
+            }
+        }
+    };
+    save_state(part_path, &state)?;
+
+    progress.set_total(Some(state.total_bytes));
+    progress.set_downloaded(state.written());
+
+    let result = transfer(&client, part_path, &mut state, progress, control).await;
+    if let Err(e) = save_state(part_path, &state) {
+        eprintln!("Failed to save segment state: {}", e);
+    }
+    match result {
+        Ok(true) => {}
+        // A segment came back as a full response: the server stopped honoring ranges or the file changed
+        Ok(false) => {
+            println!("Server did not honor range requests for {}, using a single stream", url);
+            discard_partial(part_path);
+            return resume::download_resumable(url, part_path, expected, progress, control).await;
+        }
+        Err(e) => return Err(e),
+    }
+
+    progress.set_phase(DownloadPhase::Verifying);
+    if let Err(e) = integrity::verify_file_blocking(part_path, expected).await {
+        discard_partial(part_path);
+        return Err(e);
+    }
+    let _ = fs::remove_file(state_path(part_path));
+    Ok(())
+}
+
+// Fetch every unfinished segment concurrently, writing each chunk at its offset. Returns false
+// when a range request is not answered with 206.
+async fn transfer(client: &reqwest::Client, part_path: &Path, state: &mut SegmentedDownload, progress: &mut ProgressReporter, control: &JobControl) -> Result<bool, DownloadError> {
+    let mut streams = Vec::new();
+    for (index, segment) in state.segments.iter().enumerate() {
+        if segment.is_done() {
+            continue;
+        }
+        let mut request = client.get(&state.url)
+            .header(reqwest::header::RANGE, format!("bytes={}-{}", segment.start + segment.written, segment.end));
+        if let Some(etag) = &state.etag {
+            request = request.header(reqwest::header::IF_RANGE, etag.as_str());
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            return Ok(false);
+        }
+        streams.push(response.bytes_stream().map(move |chunk| (index, chunk)).boxed());
+    }
+
+    let mut file = fs::OpenOptions::new().write(true).open(part_path).map_err(|e| e.to_string())?;
+    let mut merged = futures_util::stream::select_all(streams);
+    let mut last_save = Instant::now();
+    while let Some((index, chunk)) = merged.next().await {
+        control.check()?;
+        let chunk = chunk.map_err(|e| e.to_string())?;
+        control.throttle(chunk.len()).await;
+        let segment = &mut state.segments[index];
+        // Never let a misbehaving server write into the next segment
+        let length = cmp::min(chunk.len() as u64, segment.len() - segment.written) as usize;
+        file.seek(SeekFrom::Start(segment.start + segment.written)).map_err(|e| e.to_string())?;
+        file.write_all(&chunk[..length]).map_err(|e| e.to_string())?;
+        segment.written += length as u64;
+        progress.advance(length as u64);
+
+        if last_save.elapsed() >= SAVE_INTERVAL {
+            file.flush().map_err(|e| e.to_string())?;
+            save_state(part_path, state)?;
+            last_save = Instant::now();
+        }
+    }
+    file.flush().map_err(|e| e.to_string())?;
+
+    if let Some(segment) = state.segments.iter().find(|s| !s.is_done()) {
+        return Err(format!("Download incomplete: segment at byte {} ended early", segment.start).into());
+    }
+    Ok(true)
+}
@@ -0,0 +1,30 @@
+use std::path::Path;
+
+use super::extract::ExtractionMode;
+use super::DownloadError;
+
+fn format_size(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+// Space an install needs. The extracted size is unknown up front, so it is assumed to be at
+// least the archive size; the cached mode also holds the archive itself until extraction ends.
+pub fn required_space(archive_size: u64, already_downloaded: u64, mode: ExtractionMode) -> u64 {
+    match mode {
+        ExtractionMode::Cached => archive_size.saturating_sub(already_downloaded) + archive_size,
+        ExtractionMode::Streaming => archive_size,
+    }
+}
+
+// Fail before downloading when the disk holding `dir` cannot fit the install
+pub fn ensure_space(dir: &Path, required: u64) -> Result<(), DownloadError> {
+    let available = fs2::available_space(dir).map_err(|e| e.to_string())?;
+    if available < required {
+        return Err(format!(
+            "Not enough disk space: {} needed, {} available",
+            format_size(required),
+            format_size(available)
+        ).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Installs are extracted here first. It lives inside the games folder so moving the result
+// into place is a rename on the same drive.
+pub const STAGING_DIR_NAME: &str = ".staging";
+
+pub fn staging_dir(game_base_dir: &Path, folder_name: &str) -> PathBuf {
+    game_base_dir.join(STAGING_DIR_NAME).join(folder_name)
+}
+
+// Empty staging folder for an install, dropping whatever an earlier attempt left there
+pub fn prepare(game_base_dir: &Path, folder_name: &str) -> Result<PathBuf, String> {
+    let dir = staging_dir(game_base_dir, folder_name);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).map_err(|e| format!("Failed to clear staging folder {:?}: {}", dir, e))?;
+    }
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+// An install moved aside during a swap: ".<folder>.old-<timestamp>" next to it, hidden from the
+// install scan and outside the staging folder, so it is never deleted with leftovers
+const ASIDE_MARKER: &str = ".old-";
+
+fn aside_path(target: &Path) -> PathBuf {
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    target.with_file_name(format!(".{}{}{}", name, ASIDE_MARKER, chrono::Utc::now().timestamp()))
+}
+
+// Folder an aside copy was moved out of
+fn aside_target(aside: &Path) -> Option<PathBuf> {
+    let name = aside.file_name()?.to_str()?.strip_prefix('.')?;
+    let (folder, timestamp) = name.rsplit_once(ASIDE_MARKER)?;
+    if folder.is_empty() || timestamp.is_empty() || !timestamp.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(aside.with_file_name(folder))
+}
+
+// Move a staged install to `target`. An existing install there is moved aside first and only
+// deleted once the new one is in place, so a failed swap leaves the old version intact.
+pub fn commit(staged: &Path, target: &Path) -> Result<(), String> {
+    let previous = if target.exists() {
+        let aside = aside_path(target);
+        fs::rename(target, &aside).map_err(|e| format!("Failed to move {:?} aside: {}", target, e))?;
+        Some(aside)
+    } else {
+        None
+    };
+
+    if let Err(e) = fs::rename(staged, target) {
+        if let Some(aside) = &previous {
+            if let Err(restore) = fs::rename(aside, target) {
+                eprintln!("Failed to restore {:?}: {}", target, restore);
+            }
+        }
+        return Err(format!("Failed to move install into {:?}: {}", target, e));
+    }
+
+    // Anything that cannot be removed now is cleaned up on the next start
+    if let Some(aside) = previous {
+        if let Err(e) = fs::remove_dir_all(&aside) {
+            eprintln!("Failed to remove previous install {:?}: {}", aside, e);
+        }
+    }
+    Ok(())
+}
+
+// Put back installs a swap moved aside without finishing (a crash between the two renames or a
+// failed restore), or remove the aside copy when the swap did complete
+fn restore_asides(game_base_dir: &Path) {
+    let entries = match fs::read_dir(game_base_dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for aside in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        let target = match aside_target(&aside) {
+            Some(target) if aside.is_dir() => target,
+            _ => continue,
+        };
+        if target.exists() {
+            if let Err(e) = fs::remove_dir_all(&aside) {
+                eprintln!("Failed to remove previous install {:?}: {}", aside, e);
+            }
+        } else {
+            match fs::rename(&aside, &target) {
+                Ok(()) => println!("Restored {:?} from an interrupted update", target),
+                Err(e) => eprintln!("Failed to restore {:?}: {}", target, e),
+            }
+        }
+    }
+}
+
+// Remove staging folders left behind by interrupted installs
+pub fn cleanup_leftovers(game_base_dir: &Path) {
+    restore_asides(game_base_dir);
+    let root = game_base_dir.join(STAGING_DIR_NAME);
+    if !root.exists() {
+        return;
+    }
+    match fs::remove_dir_all(&root) {
+        Ok(()) => println!("Removed leftover staging folder {:?}", root),
+        Err(e) => eprintln!("Failed to remove staging folder {:?}: {}", root, e),
+    }
+}
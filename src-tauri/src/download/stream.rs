@@ -0,0 +1,121 @@
+use std::cmp;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::Arc;
+use futures_util::StreamExt;
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+
+use super::archive;
+use super::extract::{self, StreamOutcome};
+use super::integrity::ExpectedArchive;
+use super::progress::{DownloadPhase, ProgressReporter};
+use super::queue::JobControl;
+use super::DownloadError;
+
+// Chunks in flight between the download and the extractor thread
+const CHANNEL_CAPACITY: usize = 16;
+
+// Blocking reader over chunks sent from the async download
+struct ChannelReader {
+    receiver: mpsc::Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position >= self.chunk.len() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let n = cmp::min(buf.len(), self.chunk.len() - self.position);
+        buf[..n].copy_from_slice(&self.chunk[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+// Download an archive and extract it into `dest` (a staging folder) at the same time, without
+// keeping it. The hash is only known at the end, so `dest` is removed unless the archive was
+// fully extracted and verified. Pausing stops the job; it starts over when resumed.
+pub async fn download_and_extract(url: &str, dest: &Path, format: Option<&str>, expected: &ExpectedArchive, progress: &mut ProgressReporter, control: &Arc<JobControl>) -> Result<StreamOutcome, DownloadError> {
+    let result = stream_into(url, dest, format, expected, progress, control).await;
+    if !matches!(result, Ok(StreamOutcome::Extracted(_))) && dest.exists() {
+        if let Err(e) = fs::remove_dir_all(dest) {
+            eprintln!("Failed to remove unverified files in {:?}: {}", dest, e);
+        }
+    }
+    result
+}
+
+async fn stream_into(url: &str, dest: &Path, format: Option<&str>, expected: &ExpectedArchive, progress: &mut ProgressReporter, control: &Arc<JobControl>) -> Result<StreamOutcome, DownloadError> {
+    progress.set_phase(DownloadPhase::Downloading);
+    let response = reqwest::get(url).await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Download failed: HTTP {}", response.status()).into());
+    }
+
+    let total = response.content_length();
+    if let Some(total) = total {
+        expected.check_size(total)?;
+    }
+    progress.set_total(total.or(expected.size));
+    progress.set_downloaded(0);
+
+    let limit = extract::uncompressed_limit(total.or(expected.size).unwrap_or(0));
+    let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+    let extract_dest = dest.to_path_buf();
+    let declared_format = format.map(|f| f.to_string());
+    let extract_control = control.clone();
+    let extractor = tokio::task::spawn_blocking(move || {
+        let mut reader = ChannelReader { receiver, chunk: Vec::new(), position: 0 };
+        archive::extract_stream(&mut reader, declared_format.as_deref(), &extract_dest, limit, &extract_control)
+    });
+
+    let mut hasher = Sha256::new();
+    let mut written = 0u64;
+    let mut stopped = None;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        if let Err(e) = control.check() {
+            stopped = Some(e);
+            break;
+        }
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                stopped = Some(DownloadError::Failed(e.to_string()));
+                break;
+            }
+        };
+        control.throttle(chunk.len()).await;
+        hasher.update(&chunk);
+        written += chunk.len() as u64;
+        progress.advance(chunk.len() as u64);
+        // The extractor stopped early, its result says why
+        if sender.send(chunk.to_vec()).await.is_err() {
+            break;
+        }
+    }
+    drop(sender);
+
+    let outcome = extractor.await.map_err(|e| e.to_string())?;
+    if let Some(e) = stopped {
+        return Err(e);
+    }
+    let report = match outcome? {
+        StreamOutcome::Extracted(report) => report,
+        unsupported => return Ok(unsupported),
+    };
+
+    progress.set_phase(DownloadPhase::Verifying);
+    expected.check(hasher, written)?;
+    Ok(StreamOutcome::Extracted(report))
+}
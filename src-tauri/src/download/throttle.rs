@@ -0,0 +1,107 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use chrono::{Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+const TIME_FORMAT: &str = "%H:%M";
+
+// Hours of the day when queued downloads may start, e.g. 01:00 to 07:00. A window whose end is
+// before its start runs past midnight; equal times mean the whole day.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DownloadWindow {
+    pub start: String,
+    pub end: String,
+}
+
+impl DownloadWindow {
+    fn parse_time(value: &str) -> Result<NaiveTime, String> {
+        NaiveTime::parse_from_str(value.trim(), TIME_FORMAT)
+            .map_err(|_| format!("Invalid time '{}', expected HH:MM", value))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        Self::parse_time(&self.start)?;
+        Self::parse_time(&self.end)?;
+        Ok(())
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        let (start, end) = match (Self::parse_time(&self.start), Self::parse_time(&self.end)) {
+            (Ok(start), Ok(end)) => (start, end),
+            // An unreadable window never blocks downloads
+            _ => return true,
+        };
+        if start == end {
+            true
+        } else if start < end {
+            start <= time && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+
+    pub fn is_open_now(&self) -> bool {
+        self.contains(Local::now().time())
+    }
+}
+
+struct Bucket {
+    // 0 means unlimited
+    bytes_per_second: u64,
+    window: Option<DownloadWindow>,
+    // Goes negative while downloads owe time for bytes already received
+    tokens: f64,
+    last_refill: Instant,
+}
+
+// Token bucket shared by every running download, so the limit applies to the launcher as a
+// whole rather than per job or per segment. Inside the download window it lets everything through.
+pub struct RateLimiter {
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        RateLimiter {
+            bucket: Mutex::new(Bucket {
+                bytes_per_second: 0,
+                window: None,
+                tokens: 0.0,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn configure(&self, bytes_per_second: u64, window: Option<DownloadWindow>) {
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        bucket.bytes_per_second = bytes_per_second;
+        bucket.window = window;
+        bucket.tokens = 0.0;
+        bucket.last_refill = Instant::now();
+    }
+
+    // Account for `bytes` just received, sleeping long enough to keep the average under the limit
+    pub async fn acquire(&self, bytes: u64) {
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+            let full_speed = bucket.window.as_ref().map(|w| w.is_open_now()).unwrap_or(false);
+            if bucket.bytes_per_second == 0 || full_speed {
+                return;
+            }
+            let rate = bucket.bytes_per_second as f64;
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+            // At most one second of unused bandwidth can be saved up
+            bucket.tokens = (bucket.tokens + elapsed * rate).min(rate) - bytes as f64;
+            bucket.last_refill = now;
+            if bucket.tokens < 0.0 {
+                Duration::from_secs_f64(-bucket.tokens / rate)
+            } else {
+                Duration::ZERO
+            }
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::version::{compare_versions, Version};
+
+// Folder next to the launcher executable that holds every installed game
+pub const GAMES_DIR_NAME: &str = "AntChillGame";
+// Archives being downloaded, kept outside the game folders so an interrupted download can resume
+pub const DOWNLOADS_DIR_NAME: &str = ".downloads";
+// Copies of an install taken before it is updated, one folder per game id
+pub const BACKUPS_DIR_NAME: &str = ".backups";
+
+// One installed build of a game
+#[derive(Debug, Clone)]
+pub struct Install {
+  pub dir: PathBuf,
+  // From the folder name; empty for a folder named after the game alone
+  pub version: String,
+}
+
+pub fn game_base_dir() -> Result<PathBuf, String> {
+  let launcher_dir = std::env::current_exe()
+    .map_err(|e| e.to_string())?
+    .parent()
+    .ok_or("Could not get launcher directory")?
+    .to_path_buf();
+  Ok(launcher_dir.join(GAMES_DIR_NAME))
+}
+
+// Install folder name: [tên game].v[phiên bản]
+pub fn folder_name(game_name: &str, version: &str) -> String {
+  format!("{}.v{}", game_name.to_lowercase(), version)
+}
+
+pub fn backup_dir(game_base_dir: &Path, game_id: &str) -> PathBuf {
+  game_base_dir.join(BACKUPS_DIR_NAME).join(game_id)
+}
+
+// Version of `game_name` installed in `folder`, if the folder belongs to it. Besides
+// "<name>.v<version>" this accepts folders copied by hand: "<name>.<version>", a bare "<name>"
+// and any letter case. The rest has to be a version, so "brato.io.v1" is not a "brato" install.
+fn folder_version(folder: &str, game_name: &str) -> Option<String> {
+  let folder = folder.to_lowercase();
+  let name = game_name.to_lowercase();
+  if folder == name {
+    return Some(String::new());
+  }
+  let rest = folder.strip_prefix(&name)?.strip_prefix('.')?;
+  let version = rest.strip_prefix('v').unwrap_or(rest);
+  Version::parse(version)?;
+  Some(version.to_string())
+}
+
+// Every installed build of a game, oldest first. Launcher folders (staging, downloads, backups)
+// start with a dot and are never a game.
+pub fn installs(game_base_dir: &Path, game_name: &str) -> Vec<Install> {
+  let entries = match fs::read_dir(game_base_dir) {
+    Ok(entries) => entries,
+    Err(_) => return Vec::new(),
+  };
+  let mut installs: Vec<Install> = entries
+    .filter_map(|entry| entry.ok())
+    .map(|entry| entry.path())
+    .filter(|path| path.is_dir())
+    .filter_map(|dir| {
+      let folder = dir.file_name()?.to_str()?.to_string();
+      if folder.starts_with('.') {
+        return None;
+      }
+      folder_version(&folder, game_name).map(|version| Install { dir, version })
+    })
+    .collect();
+  installs.sort_by(|a, b| compare_versions(&a.version, &b.version));
+  installs
+}
+
+// Install of exactly `version`, preferring the folder the launcher itself would have created
+pub fn find_install(game_base_dir: &Path, game_name: &str, version: &str) -> Option<Install> {
+  let canonical = game_base_dir.join(folder_name(game_name, version));
+  installs(game_base_dir, game_name)
+    .into_iter()
+    .filter(|install| compare_versions(&install.version, version).is_eq())
+    .max_by_key(|install| install.dir == canonical)
+}
+
+// The build the player has: `version` when installed, otherwise the newest one
+pub fn current_install(game_base_dir: &Path, game_name: &str, version: &str) -> Option<Install> {
+  find_install(game_base_dir, game_name, version).or_else(|| installs(game_base_dir, game_name).pop())
+}
+
+// Leftover archives of a game in the downloads folder ("<folder name>.archive", ".part", ...)
+pub fn belongs_to_game(file_name: &str, game_name: &str) -> bool {
+  file_name.to_lowercase().starts_with(&format!("{}.v", game_name.to_lowercase()))
+}
@@ -1,16 +1,38 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::process::Command;
-use std::fs;
-use std::io::Write;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tauri::{CustomMenuItem, SystemTray, SystemTrayMenu, SystemTrayEvent, Manager, AppHandle};
+use tauri::{CustomMenuItem, SystemTray, SystemTrayMenu, SystemTrayEvent, Manager, AppHandle, State};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use winreg::enums::*;
 use winreg::RegKey;
 
+mod download;
+mod locations;
+mod manifest;
+mod settings;
+mod version;
+
+use download::progress::{DownloadPhase, ProgressReporter};
+use download::queue::{DownloadJobInfo, DownloadManager, JobControl};
+use download::throttle::DownloadWindow;
+use download::archive;
+use download::extract::{ExtractReport, ExtractionMode, RejectedEntry, StreamOutcome};
+use download::integrity::{self, ExpectedArchive};
+use download::{delta, segmented, space, staging, stream, DownloadError};
+use manifest::cache::{ManifestStatus, ManifestStore};
+use manifest::channels::{self, ReleaseChannel};
+use manifest::compat::{self, LauncherCompatibility};
+use manifest::diff::{self, ManifestChange};
+use manifest::files::{self, FileCheckReport, FileManifest};
+use manifest::overrides;
+use manifest::source::{self, ManifestSourcesInfo};
+use manifest::validate::{self, ValidationReport};
+use settings::{load_settings, save_settings, MAX_DOWNLOAD_SEGMENTS};
+use version::UpdateStatus;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct GameInfo {
   id: String,
@@ -19,6 +41,9 @@ struct GameInfo {
   status: String,
   download_url: Option<String>,
   executable_path: Option<String>,
+  // Version of the build found on disk by scan_local_games; `version` is the manifest's
+  #[serde(default)]
+  installed_version: Option<String>,
   image_url: String,
   logo_url: Option<String>,
   background_id: String,
@@ -28,6 +53,49 @@ struct GameInfo {
   changelog: Option<String>,
   is_coming_soon: bool,
   repair_enabled: bool,
+  // Channel of the build described by the fields above
+  #[serde(default)]
+  channel: ReleaseChannel,
+  // Additional builds, e.g. betas published ahead of the stable version
+  #[serde(default)]
+  versions: Vec<GameVersion>,
+  #[serde(default)]
+  min_launcher_version: Option<String>,
+  // Expected SHA-256 (hex) and exact byte size of the downloaded archive
+  #[serde(default)]
+  sha256: Option<String>,
+  #[serde(default)]
+  size: Option<u64>,
+  // "zip", "tar.gz", "tar.zst" or "7z"; detected from the file when missing
+  #[serde(default)]
+  archive_format: Option<String>,
+  // Per-file manifest of this build (path, size, sha256, executable) and its SHA-256
+  #[serde(default)]
+  files_url: Option<String>,
+  #[serde(default)]
+  files_sha256: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct GameVersion {
+  version: String,
+  channel: ReleaseChannel,
+  download_url: Option<String>,
+  file_size: Option<String>,
+  release_date: Option<String>,
+  changelog: Option<String>,
+  #[serde(default)]
+  min_launcher_version: Option<String>,
+  #[serde(default)]
+  sha256: Option<String>,
+  #[serde(default)]
+  size: Option<u64>,
+  #[serde(default)]
+  archive_format: Option<String>,
+  #[serde(default)]
+  files_url: Option<String>,
+  #[serde(default)]
+  files_sha256: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -39,6 +107,9 @@ struct GameManifest {
   games: Vec<GameInfo>,
   social_links: Vec<SocialLink>,
   settings: ManifestSettings,
+  // Oldest launcher allowed to install anything from this manifest
+  #[serde(default)]
+  min_launcher_version: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -81,9 +152,21 @@ struct ManifestSettings {
 struct UpdateInfo {
     current_version: String,
     latest_version: String,
+    status: UpdateStatus,
+    // Same as `status == update_available`, kept for older frontends
     needs_update: bool,
     update_url: Option<String>,
     changelog: Option<String>,
+    launcher_update_required: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct InstallResult {
+    install_path: String,
+    files_extracted: usize,
+    bytes_extracted: u64,
+    // Archive entries that were not written, e.g. paths escaping the game folder
+    rejected_entries: Vec<RejectedEntry>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -100,73 +183,67 @@ struct NetworkStatus {
   message: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct LocalManifest {
-  manifest: GameManifest,
-  last_updated: u64,
-  is_offline: bool,
+// Resolve the ordered manifest mirror list for this launcher
+fn manifest_sources(app: &AppHandle) -> Vec<String> {
+  source::resolve_sources(&load_settings(app)).0
 }
 
-// Global variable to store local manifest
-static mut LOCAL_MANIFEST: Option<LocalManifest> = None;
+// Release channel selected in launcher settings
+fn release_channel(app: &AppHandle) -> ReleaseChannel {
+  load_settings(app).release_channel
+}
 
-// Check network connectivity
-async fn check_network() -> bool {
-  // Try to ping the manifest URL instead of httpbin
-  let manifest_url = "https://pub-72a5a57231ae489cb74409bdc120cb93.r2.dev/manifest.json";
-  match reqwest::get(manifest_url).await {
-    Ok(response) => response.status().is_success(),
-    Err(_) => {
-      // Fallback to Google DNS
-      match reqwest::get("https://8.8.8.8").await {
-        Ok(_) => true,
-        Err(_) => false,
-      }
+// Version of the running launcher (package.version in tauri.conf.json), compared against
+// min_launcher_version
+fn launcher_version(app: &AppHandle) -> String {
+  app.package_info().version.to_string()
+}
+
+// Games resolved for the selected channel, flagged when this launcher is too old for them
+fn visible_games(app: &AppHandle, manifest: &GameManifest) -> Vec<GameInfo> {
+  let mut games = channels::games_for_channel(manifest, release_channel(app));
+  compat::mark_incompatible_games(manifest, &mut games, &launcher_version(app));
+  games
+}
+
+// Fetch a verified manifest, falling back to the last verified local copy
+async fn current_manifest(app: &AppHandle, store: &ManifestStore) -> Result<GameManifest, String> {
+  match store.refresh(&manifest_sources(app)).await {
+    Ok(manifest) => Ok(manifest),
+    Err(e) => {
+      eprintln!("Failed to fetch online manifest: {}", e);
+      store.manifest().ok_or(e)
     }
   }
 }
 
-// Save manifest to local storage
-fn save_local_manifest(manifest: &GameManifest) -> Result<(), String> {
-  let current_time = SystemTime::now()
-    .duration_since(UNIX_EPOCH)
-    .map_err(|e| e.to_string())?
-    .as_secs();
-  
-  let local_manifest = LocalManifest {
-    manifest: manifest.clone(),
-    last_updated: current_time,
-    is_offline: false,
-  };
-  
-  // Save to file for persistence
-  let app_dir = tauri::api::path::app_dir(&tauri::Config::default())
-    .ok_or("Failed to get app directory")?;
-  let manifest_path = app_dir.join("local_manifest.json");
-  
-  let manifest_json = serde_json::to_string_pretty(&local_manifest)
-    .map_err(|e| e.to_string())?;
-  
-  fs::write(manifest_path, manifest_json)
-    .map_err(|e| e.to_string())?;
-  
-  unsafe {
-    LOCAL_MANIFEST = Some(local_manifest);
-  }
-  
-  Ok(())
+// A game as the selected channel sees it
+async fn manifest_game(app: &AppHandle, store: &ManifestStore, game_id: &str) -> Result<GameInfo, String> {
+  let manifest = current_manifest(app, store).await?;
+  channels::games_for_channel(&manifest, release_channel(app))
+    .into_iter()
+    .find(|g| g.id == game_id)
+    .ok_or_else(|| "Game not found in manifest".to_string())
 }
 
-// Load manifest from local storage
-fn load_local_manifest() -> Option<GameManifest> {
-  unsafe {
-    LOCAL_MANIFEST.as_ref().map(|lm| lm.manifest.clone())
+// Check network connectivity
+async fn check_network(app: &AppHandle, store: &ManifestStore) -> bool {
+  // Try to reach a manifest mirror instead of httpbin
+  if store.refresh(&manifest_sources(app)).await.is_ok() {
+    return true;
+  }
+
+  // Fallback to Google DNS
+  match reqwest::get("https://8.8.8.8").await {
+    Ok(_) => true,
+    Err(_) => false,
   }
 }
 
 // Get manifest file path
-fn get_manifest_path() -> Result<PathBuf, String> {
-  let app_dir = tauri::api::path::app_dir(&tauri::Config::default())
+fn get_manifest_path(app: &AppHandle) -> Result<PathBuf, String> {
+  let app_dir = app.path_resolver()
+    .app_data_dir()
     .ok_or("Failed to get app directory")?;
   Ok(app_dir.join("local_manifest.json"))
 }
@@ -213,80 +290,413 @@ fn is_startup_with_windows() -> Result<bool, String> {
     }
 }
 
+// Queue the download and wait for it, pausing keeps this call pending until resumed. Only the
+// URL from the verified manifest is ever downloaded; `download_url` is what the frontend showed.
+async fn queue_download(app: &AppHandle, manager: &DownloadManager, game: &GameInfo, download_url: &str) -> Result<InstallResult, String> {
+    let verified_url = game.download_url.as_deref().ok_or("Game has no download in the manifest")?;
+    if verified_url != download_url {
+        return Err("Download URL does not match the signed manifest".to_string());
+    }
+    let (job_id, finished) = manager.enqueue(app, &game.id, verified_url)?;
+    println!("Queued download {} for {}", job_id, game.id);
+    finished.await.map_err(|_| "Download was dropped".to_string())?
+}
+
 #[tauri::command]
-async fn download_game(game_id: String, download_url: String) -> Result<String, String> {
-    // Create AntChillGame directory next to launcher executable
-    let launcher_dir = std::env::current_exe()
-        .map_err(|e| e.to_string())?
-        .parent()
-        .ok_or("Could not get launcher directory")?
-        .to_path_buf();
-    let game_base_dir = launcher_dir.join("AntChillGame");
+async fn download_game(app: AppHandle, store: State<'_, ManifestStore>, manager: State<'_, DownloadManager>, game_id: String, download_url: String) -> Result<InstallResult, String> {
+    let game_info = manifest_game(&app, &store, &game_id).await?;
+    queue_download(&app, &manager, &game_info, &download_url).await
+}
+
+#[tauri::command]
+fn list_downloads(manager: State<'_, DownloadManager>) -> Vec<DownloadJobInfo> {
+    manager.list()
+}
+
+#[tauri::command]
+fn pause_download(app: AppHandle, manager: State<'_, DownloadManager>, job_id: u64) -> Result<(), String> {
+    manager.pause(&app, job_id)
+}
+
+#[tauri::command]
+fn resume_download(app: AppHandle, manager: State<'_, DownloadManager>, job_id: u64) -> Result<(), String> {
+    manager.resume(&app, job_id)
+}
+
+#[tauri::command]
+fn cancel_download(app: AppHandle, manager: State<'_, DownloadManager>, job_id: u64) -> Result<(), String> {
+    manager.cancel(&app, job_id)
+}
+
+#[tauri::command]
+fn move_download(app: AppHandle, manager: State<'_, DownloadManager>, job_id: u64, position: usize) -> Result<(), String> {
+    manager.move_to(&app, job_id, position)
+}
+
+#[tauri::command]
+fn get_extraction_mode(app: AppHandle) -> Result<ExtractionMode, String> {
+    Ok(load_settings(&app).extraction_mode)
+}
+
+#[tauri::command]
+fn set_extraction_mode(app: AppHandle, mode: ExtractionMode) -> Result<(), String> {
+    let mut settings = load_settings(&app);
+    settings.extraction_mode = mode;
+    save_settings(&app, &settings)
+}
+
+#[tauri::command]
+fn get_manifest_override_enabled(app: AppHandle) -> Result<bool, String> {
+    Ok(load_settings(&app).manifest_override_enabled)
+}
+
+// Developer opt-in to apply manifest.override.json; takes effect immediately
+#[tauri::command]
+fn set_manifest_override_enabled(app: AppHandle, store: State<'_, ManifestStore>, enabled: bool) -> Result<(), String> {
+    let mut settings = load_settings(&app);
+    settings.manifest_override_enabled = enabled;
+    save_settings(&app, &settings)?;
+    store.set_override_enabled(enabled);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_max_concurrent_downloads(app: AppHandle) -> Result<usize, String> {
+    Ok(load_settings(&app).max_concurrent_downloads)
+}
+
+#[tauri::command]
+fn set_max_concurrent_downloads(app: AppHandle, manager: State<'_, DownloadManager>, value: usize) -> Result<(), String> {
+    if value == 0 {
+        return Err("At least one download must be allowed".to_string());
+    }
+    let mut settings = load_settings(&app);
+    settings.max_concurrent_downloads = value;
+    save_settings(&app, &settings)?;
+    manager.schedule(&app);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_download_segments(app: AppHandle) -> Result<usize, String> {
+    Ok(load_settings(&app).download_segments)
+}
+
+#[tauri::command]
+fn set_download_segments(app: AppHandle, value: usize) -> Result<(), String> {
+    if value == 0 || value > MAX_DOWNLOAD_SEGMENTS {
+        return Err(format!("Segments must be between 1 and {}", MAX_DOWNLOAD_SEGMENTS));
+    }
+    let mut settings = load_settings(&app);
+    settings.download_segments = value;
+    save_settings(&app, &settings)
+}
+
+#[tauri::command]
+fn get_download_speed_limit(app: AppHandle) -> Result<u64, String> {
+    Ok(load_settings(&app).download_speed_limit_kbps)
+}
+
+// Limit in KiB/s shared by all downloads, 0 to remove it; applies to running downloads too
+#[tauri::command]
+fn set_download_speed_limit(app: AppHandle, manager: State<'_, DownloadManager>, kbps: u64) -> Result<(), String> {
+    let mut settings = load_settings(&app);
+    settings.download_speed_limit_kbps = kbps;
+    save_settings(&app, &settings)?;
+    manager.apply_limits(&settings);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_download_window(app: AppHandle) -> Result<Option<DownloadWindow>, String> {
+    Ok(load_settings(&app).download_window)
+}
+
+#[tauri::command]
+fn set_download_window(app: AppHandle, manager: State<'_, DownloadManager>, window: Option<DownloadWindow>) -> Result<(), String> {
+    if let Some(window) = &window {
+        window.validate()?;
+    }
+    let mut settings = load_settings(&app);
+    settings.download_window = window;
+    save_settings(&app, &settings)?;
+    manager.apply_limits(&settings);
+    manager.schedule(&app);
+    Ok(())
+}
+
+// Store the build's file manifest with a fresh install, but only if the installed files match it
+fn keep_file_manifest(install_dir: &Path, file_manifest: &FileManifest) -> Result<(), String> {
+    let check = files::check_files(install_dir, file_manifest)?;
+    if !check.is_clean() {
+        return Err(format!("{} files missing and {} different from the archive", check.missing.len(), check.modified.len()));
+    }
+    files::apply_permissions(install_dir, file_manifest)?;
+    files::save_installed(install_dir, file_manifest)
+}
+
+// Installed build of a game that has a stored file manifest, preferring the target folder itself
+// (a reinstall) and otherwise the newest version
+fn find_previous_install(game_base_dir: &Path, game: &GameInfo, game_folder_name: &str) -> Option<(PathBuf, FileManifest)> {
+    locations::installs(game_base_dir, &game.name)
+        .into_iter()
+        .filter_map(|install| files::load_installed(&install.dir).map(|installed| (install.dir, installed)))
+        .max_by(|(a_path, a), (b_path, b)| {
+            let a_target = a_path.ends_with(game_folder_name);
+            let b_target = b_path.ends_with(game_folder_name);
+            a_target.cmp(&b_target).then_with(|| version::compare_versions(&a.version, &b.version))
+        })
+}
+
+// File manifests of the builds published strictly between two versions, oldest first, so patches
+// can be chained across them. Builds whose file manifest cannot be fetched or is not pinned by a
+// files_sha256 are left out.
+async fn intermediate_file_manifests(game: &GameInfo, installed: &str, target: &str) -> Vec<FileManifest> {
+    let top_level = (game.version.as_str(), game.files_url.as_deref(), game.files_sha256.as_deref());
+    let mut builds: Vec<(&str, &str, &str)> = std::iter::once(top_level)
+        .chain(game.versions.iter().map(|v| (v.version.as_str(), v.files_url.as_deref(), v.files_sha256.as_deref())))
+        .filter(|(version, _, _)| version::compare_versions(version, installed).is_gt() && version::compare_versions(version, target).is_lt())
+        .filter_map(|(version, files_url, files_sha256)| Some((version, files_url?, files_sha256?)))
+        .collect();
+    builds.sort_by(|a, b| version::compare_versions(a.0, b.0));
+    builds.dedup_by(|a, b| version::compare_versions(a.0, b.0).is_eq());
+
+    let mut manifests = Vec::new();
+    for (version, files_url, files_sha256) in builds {
+        match files::fetch(files_url, files_sha256).await {
+            Ok(manifest) => manifests.push(manifest),
+            Err(e) => eprintln!("Skipping file manifest of {} {}: {}", game.id, version, e),
+        }
+    }
+    manifests
+}
+
+// Build the target version in staging from an older install, patching or downloading only the
+// files that changed. `game` is the game as published, with all of its builds. None when there is
+// nothing to start from or the delta would not save anything.
+async fn delta_update(game_base_dir: &Path, game: &GameInfo, game_folder_name: &str, target: &FileManifest, archive_size: Option<u64>, progress: &mut ProgressReporter, control: &JobControl) -> Result<Option<ExtractReport>, DownloadError> {
+    let (installed_dir, installed) = match find_previous_install(game_base_dir, game, game_folder_name) {
+        Some(previous) => previous,
+        None => return Ok(None),
+    };
+    let has_patches = target.files.iter().any(|f| !f.patches.is_empty());
+    if target.base_url.is_none() && !has_patches {
+        return Ok(None);
+    }
+    let intermediate = if has_patches {
+        intermediate_file_manifests(game, &installed.version, &target.version).await
+    } else {
+        Vec::new()
+    };
+    let plan = delta::plan(&installed, &intermediate, target);
+    if !plan.download.is_empty() && target.base_url.is_none() {
+        return Ok(None);
+    }
+    if archive_size.map(|size| plan.download_size() >= size).unwrap_or(false) {
+        return Ok(None);
+    }
+
+    println!(
+        "Delta update of {} from {}: {} files reused, {} patched, {} to download ({} bytes), {} removed",
+        game.id, installed.version, plan.reuse.len(), plan.patch.len(), plan.download.len(), plan.download_size(), plan.removed.len()
+    );
+    let staged = staging::prepare(game_base_dir, game_folder_name)?;
+    let report = delta::apply(&plan, &installed_dir, target.base_url.as_deref(), &staged, progress, control).await?;
+    Ok(Some(report))
+}
+
+// Get the archive into `archive_path`, either downloaded (in parallel ranges when the server
+// allows it, resuming a partial download) or copied from a local build while a developer
+// override is active
+async fn fetch_archive(app: &AppHandle, download_url: &str, archive_path: &Path, part_path: &Path, expected: &ExpectedArchive, progress: &mut ProgressReporter, control: &JobControl) -> Result<(), DownloadError> {
+    if download_url.starts_with("file://") {
+        // Local builds are only allowed while a developer override is active
+        if !app.state::<ManifestStore>().override_active() {
+            return Err("file:// downloads require a manifest override".into());
+        }
+        let source_path = reqwest::Url::parse(download_url)
+            .ok()
+            .and_then(|url| url.to_file_path().ok())
+            .ok_or("Invalid file:// URL")?;
+        let target = archive_path.to_path_buf();
+        tokio::task::spawn_blocking(move || std::fs::copy(&source_path, &target))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+        progress.set_phase(DownloadPhase::Verifying);
+        integrity::verify_file_blocking(archive_path, expected).await?;
+    } else {
+        // Download file with progress events, continuing a previous partial download if any.
+        // A corrupted archive is discarded and downloaded once more from scratch.
+        let segments = load_settings(app).download_segments;
+        match segmented::download_segmented(download_url, part_path, segments, expected, progress, control).await {
+            Err(DownloadError::Integrity(e)) => {
+                eprintln!("Integrity check failed for {}, retrying: {}", download_url, e);
+                segmented::download_segmented(download_url, part_path, segments, expected, progress, control).await?;
+            }
+            result => result?,
+        }
+        std::fs::rename(part_path, archive_path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// Download and extract a game; run by the download queue
+async fn install_game(app: AppHandle, game_id: &str, control: &Arc<JobControl>) -> Result<InstallResult, DownloadError> {
+    let store = app.state::<ManifestStore>();
+    let game_base_dir = locations::game_base_dir()?;
     std::fs::create_dir_all(&game_base_dir).map_err(|e| e.to_string())?;
     
     // Get game info to create proper folder structure
-    let manifest_url = "https://pub-72a5a57231ae489cb74409bdc120cb93.r2.dev/manifest.json";
-    let manifest_response = reqwest::get(manifest_url).await.map_err(|e| e.to_string())?;
-    let manifest: GameManifest = manifest_response.json().await.map_err(|e| e.to_string())?;
+    let manifest = current_manifest(&app, &store).await?;
     
-    let game_info = manifest.games.iter()
+    let game_info = channels::games_for_channel(&manifest, release_channel(&app))
+        .into_iter()
         .find(|g| g.id == game_id)
         .ok_or("Game not found in manifest")?;
+    // Always the signed URL, never one passed in from the frontend
+    let download_url = game_info.download_url.as_deref().ok_or("Game has no download in the manifest")?;
+
+    // Refuse builds this launcher cannot handle before touching the disk
+    let compatibility = compat::check_game(&manifest, &game_info, &launcher_version(&app));
+    if !compatibility.compatible {
+        return Err(compatibility.message.unwrap_or_else(|| "Launcher update required".to_string()).into());
+    }
     
-    // Create game directory with pattern: [tên game].[phiên bản]
-    let game_name_lower = game_info.name.to_lowercase();
-    let game_folder_name = format!("{}.v{}", game_name_lower, game_info.version);
+    // Game directory with pattern: [tên game].[phiên bản], only created once the install is complete
+    let game_folder_name = locations::folder_name(&game_info.name, &game_info.version);
     let games_dir = game_base_dir.join(&game_folder_name);
-    std::fs::create_dir_all(&games_dir).map_err(|e| e.to_string())?;
-    
-    let zip_path = games_dir.join("game.zip");
-    
-    // Download file with progress tracking
-    let response = reqwest::get(&download_url).await.map_err(|e| e.to_string())?;
-    let total_size = response.content_length().unwrap_or(0);
+    control.track(staging::staging_dir(&game_base_dir, &game_folder_name));
     
-    let mut downloaded: u64 = 0;
-    let mut stream = response.bytes_stream();
-    let mut file = std::fs::File::create(&zip_path).map_err(|e| e.to_string())?;
-    
-    use futures_util::StreamExt;
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|e| e.to_string())?;
-        file.write_all(&chunk).map_err(|e| e.to_string())?;
-        downloaded += chunk.len() as u64;
-        
-        // Log progress (in a real implementation, you'd emit events to frontend)
-        if total_size > 0 {
-            let progress = (downloaded as f64 / total_size as f64) * 100.0;
-            println!("Download progress: {:.1}%", progress);
+    // Archives are kept outside the game folder so an interrupted download can resume
+    let downloads_dir = game_base_dir.join(locations::DOWNLOADS_DIR_NAME);
+    std::fs::create_dir_all(&downloads_dir).map_err(|e| e.to_string())?;
+    let archive_path = downloads_dir.join(format!("{}.archive", game_folder_name));
+    let part_path = downloads_dir.join(format!("{}.archive.part", game_folder_name));
+    control.track(archive_path.clone());
+    control.track(part_path.clone());
+    let mut progress = ProgressReporter::new(app.clone(), game_id);
+    let expected = ExpectedArchive::for_game(&game_info);
+    let archive_format = game_info.archive_format.as_deref();
+    let is_local = download_url.starts_with("file://");
+    let mode = if is_local { ExtractionMode::Cached } else { load_settings(&app).extraction_mode };
+
+    // Fail early instead of halfway through when the disk is too small
+    let archive_size = expected.size.or_else(|| game_info.file_size.as_deref().and_then(validate::parse_file_size));
+    if let Some(archive_size) = archive_size {
+        let partial = std::fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0);
+        space::ensure_space(&game_base_dir, space::required_space(archive_size, partial, mode))?;
+    }
+
+    // The per-file manifest enables delta updates and is kept with the install. Only one pinned
+    // by the signed manifest is used.
+    let file_manifest = match (&game_info.files_url, &game_info.files_sha256) {
+        (Some(files_url), Some(files_sha256)) => match files::fetch(files_url, files_sha256).await {
+            Ok(file_manifest) => Some(file_manifest),
+            Err(e) => {
+                eprintln!("Continuing without the file manifest of {}: {}", game_id, e);
+                None
+            }
+        },
+        (Some(_), None) => {
+            eprintln!("Ignoring the file manifest of {}: it has no files_sha256", game_id);
+            None
+        }
+        _ => None,
+    };
+
+    // Reuse an older install where possible; anything going wrong falls back to the full archive
+    let mut prepared = None;
+    if let Some(target) = file_manifest.as_ref().filter(|_| !is_local) {
+        let published = manifest.games.iter().find(|g| g.id == game_id).unwrap_or(&game_info);
+        match delta_update(&game_base_dir, published, &game_folder_name, target, archive_size, &mut progress, control).await {
+            Ok(report) => prepared = report,
+            Err(e @ DownloadError::Paused) | Err(e @ DownloadError::Cancelled) => return Err(e),
+            Err(e) => eprintln!("Delta update of {} failed, downloading the full archive: {}", game_id, e),
         }
     }
-    
-    // Extract zip
-    let file = std::fs::File::open(&zip_path).map_err(|e| e.to_string())?;
-    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
-    
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
-        let outpath = games_dir.join(file.name());
-        
-        if file.name().ends_with('/') {
-            std::fs::create_dir_all(&outpath).map_err(|e| e.to_string())?;
-        } else {
-            if let Some(p) = outpath.parent() {
-                if !p.exists() {
-                    std::fs::create_dir_all(&p).map_err(|e| e.to_string())?;
-                }
+
+    if prepared.is_none() && mode == ExtractionMode::Streaming {
+        let staged = staging::prepare(&game_base_dir, &game_folder_name)?;
+        let mut outcome = stream::download_and_extract(download_url, &staged, archive_format, &expected, &mut progress, control).await;
+        if let Err(DownloadError::Integrity(e)) = &outcome {
+            eprintln!("Integrity check failed for {}, retrying: {}", game_id, e);
+            let staged = staging::prepare(&game_base_dir, &game_folder_name)?;
+            outcome = stream::download_and_extract(download_url, &staged, archive_format, &expected, &mut progress, control).await;
+        }
+        match outcome? {
+            StreamOutcome::Extracted(report) => prepared = Some(report),
+            StreamOutcome::Unsupported(reason) => {
+                println!("Cannot extract {} while streaming ({}), downloading the archive first", game_id, reason);
             }
-            let mut outfile = std::fs::File::create(&outpath).map_err(|e| e.to_string())?;
-            std::io::copy(&mut file, &mut outfile).map_err(|e| e.to_string())?;
         }
     }
+
+    let report = match prepared {
+        Some(report) => report,
+        None => {
+            fetch_archive(&app, download_url, &archive_path, &part_path, &expected, &mut progress, control).await?;
+
+            // Extract, skipping entries that would escape the game folder
+            progress.set_phase(DownloadPhase::Extracting);
+            let staged = staging::prepare(&game_base_dir, &game_folder_name)?;
+            let (archive, format, extract_control) = (archive_path.clone(), archive_format.map(str::to_string), Arc::clone(control));
+            let report = tokio::task::spawn_blocking(move || archive::extract_file(&archive, format.as_deref(), &staged, &extract_control))
+                .await
+                .map_err(|e| e.to_string())??;
+
+            // Remove the archive
+            std::fs::remove_file(&archive_path).map_err(|e| e.to_string())?;
+            report
+        }
+    };
+    if !report.rejected_entries.is_empty() {
+        println!("Skipped {} unsafe archive entries for {}", report.rejected_entries.len(), game_id);
+    }
     
-    // Remove zip file
-    std::fs::remove_file(&zip_path).map_err(|e| e.to_string())?;
+    // Only a complete, verified install replaces what is in the game folder. The archive hash was
+    // checked while downloading; the staged files are checked against the file manifest.
+    progress.set_phase(DownloadPhase::Verifying);
+    if report.files_extracted == 0 {
+        return Err("Archive did not contain any files".into());
+    }
+    match file_manifest {
+        Some(file_manifest) => {
+            let staged = staging::staging_dir(&game_base_dir, &game_folder_name);
+            tokio::task::spawn_blocking(move || keep_file_manifest(&staged, &file_manifest))
+                .await
+                .map_err(|e| e.to_string())?
+                .map_err(|e| DownloadError::Integrity(format!("Installed files do not match the file manifest: {}", e)))?;
+        }
+        None if expected.sha256.is_none() => {
+            eprintln!("{} has neither an archive hash nor a file manifest, installing unverified", game_id);
+        }
+        None => {}
+    }
+    progress.set_phase(DownloadPhase::Finalizing);
+    staging::commit(&staging::staging_dir(&game_base_dir, &game_folder_name), &games_dir)?;
     
-    Ok(games_dir.to_string_lossy().to_string())
+    progress.set_phase(DownloadPhase::Completed);
+    Ok(InstallResult {
+        install_path: games_dir.to_string_lossy().to_string(),
+        files_extracted: report.files_extracted,
+        bytes_extracted: report.bytes_extracted,
+        rejected_entries: report.rejected_entries,
+    })
+}
+
+// Compare an installed game against the file manifest stored with it, listing missing and
+// modified files
+#[tauri::command]
+async fn verify_game_files(app: AppHandle, store: State<'_, ManifestStore>, game_id: String) -> Result<FileCheckReport, String> {
+    let game_info = manifest_game(&app, &store, &game_id).await?;
+    let install_dir = locations::find_install(&locations::game_base_dir()?, &game_info.name, &game_info.version)
+        .ok_or("Game is not installed")?
+        .dir;
+    let file_manifest = files::load_installed(&install_dir).ok_or("No file manifest was stored for this install")?;
+    tokio::task::spawn_blocking(move || files::check_files(&install_dir, &file_manifest))
+        .await
+        .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
@@ -311,49 +721,28 @@ async fn launch_game(executable_path: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-async fn get_games() -> Result<Vec<GameInfo>, String> {
+async fn get_games(app: AppHandle, store: State<'_, ManifestStore>) -> Result<Vec<GameInfo>, String> {
   // Check network first
-  if !check_network().await {
+  if !check_network(&app, &store).await {
     // Offline mode - try to load from local storage
-    if let Some(local_manifest) = load_local_manifest() {
+    store.mark_offline("No internet connection");
+    if let Some(local_manifest) = store.manifest() {
       println!("Using local manifest (offline mode)");
-      return Ok(local_manifest.games);
+      return Ok(visible_games(&app, &local_manifest));
     }
     return Err("No internet connection and no local manifest available.".to_string());
   }
 
-  // Online mode - try to fetch latest manifest with 1 minute timeout
-  let manifest_url = "https://pub-72a5a57231ae489cb74409bdc120cb93.r2.dev/manifest.json";
-  
-  match tokio::time::timeout(
-    std::time::Duration::from_secs(60),
-    reqwest::get(manifest_url)
-  ).await {
-    Ok(Ok(response)) => {
-      if response.status().is_success() {
-        match response.json::<GameManifest>().await {
-          Ok(manifest) => {
-            println!("Successfully loaded online manifest with {} games", manifest.games.len());
-            
-            // Save to local storage for offline use
-            if let Err(e) = save_local_manifest(&manifest) {
-              eprintln!("Failed to save local manifest: {}", e);
-            }
-            
-            return Ok(manifest.games);
-          }
-          Err(e) => eprintln!("Failed to parse online manifest: {}", e),
-        }
-      }
-    }
-    Ok(Err(e)) => eprintln!("Failed to fetch online manifest: {}", e),
-    Err(_) => eprintln!("Online manifest timeout (1 minute), using offline"),
+  // Online mode - conditional fetch across mirrors, shared with the network check above
+  match store.refresh(&manifest_sources(&app)).await {
+    Ok(manifest) => return Ok(visible_games(&app, &manifest)),
+    Err(e) => eprintln!("Failed to fetch online manifest: {}", e),
   }
   
   // Fallback to local manifest if online fetch fails
-  if let Some(local_manifest) = load_local_manifest() {
+  if let Some(local_manifest) = store.manifest() {
     println!("Using local manifest as fallback");
-    return Ok(local_manifest.games);
+    return Ok(visible_games(&app, &local_manifest));
   }
   
   // Final fallback to hardcoded data
@@ -371,6 +760,7 @@ async fn get_offline_games() -> Result<Vec<GameInfo>, String> {
       status: "available".to_string(),
       download_url: Some("https://pub-72a5a57231ae489cb74409bdc120cb93.r2.dev/games/brato_io_v0.01.zip".to_string()),
       executable_path: None,
+      installed_version: None,
       image_url: "https://files.catbox.moe/2d8fvz.png".to_string(),
       logo_url: Some("https://lh3.googleusercontent.com/pw/AP1GczMLJwyjMDaF7xJ3VS2zGuKTDnzoEBZ57qgNT39c9_kthr_5POsfSnR0Wpacn9tz4CeYjciAuAIZPDO7N67wUswUC7cDpJTymmKlxH2ehuTHvwoUcyM=w2400".to_string()),
       background_id: "brato_io_bg".to_string(),
@@ -380,6 +770,14 @@ async fn get_offline_games() -> Result<Vec<GameInfo>, String> {
       changelog: Some("Initial release with space exploration mechanics.".to_string()),
       is_coming_soon: false,
       repair_enabled: true,
+      channel: ReleaseChannel::Stable,
+      versions: vec![],
+      min_launcher_version: None,
+      sha256: None,
+      size: None,
+      archive_format: None,
+      files_url: None,
+      files_sha256: None,
     },
     GameInfo {
       id: "antknow".to_string(),
@@ -388,6 +786,7 @@ async fn get_offline_games() -> Result<Vec<GameInfo>, String> {
       status: "coming_soon".to_string(),
       download_url: None,
       executable_path: None,
+      installed_version: None,
       image_url: "https://files.catbox.moe/6f2nc5.png".to_string(),
       logo_url: Some("https://lh3.googleusercontent.com/pw/AP1GczNZq-auYxUvVXyPKce-MVkITxLbwAkSv3IJLLwH7toRhEo_8oEHI4R0Vs9-lVluYDBcpEG0I2oIR_dSqRJXMkO5ibytMLneSvCxppwCus9boxvEqM=w2400".to_string()),
       background_id: "antknow_bg".to_string(),
@@ -397,6 +796,14 @@ async fn get_offline_games() -> Result<Vec<GameInfo>, String> {
       changelog: None,
       is_coming_soon: true,
       repair_enabled: false,
+      channel: ReleaseChannel::Stable,
+      versions: vec![],
+      min_launcher_version: None,
+      sha256: None,
+      size: None,
+      archive_format: None,
+      files_url: None,
+      files_sha256: None,
     },
   ];
   
@@ -404,9 +811,9 @@ async fn get_offline_games() -> Result<Vec<GameInfo>, String> {
 }
 
 #[tauri::command]
-async fn get_social_links() -> Result<Vec<SocialLink>, String> {
+async fn get_social_links(store: State<'_, ManifestStore>) -> Result<Vec<SocialLink>, String> {
   // Try to get from local manifest first
-  if let Some(local_manifest) = load_local_manifest() {
+  if let Some(local_manifest) = store.manifest() {
     return Ok(local_manifest.social_links);
   }
   
@@ -458,9 +865,9 @@ async fn get_social_links() -> Result<Vec<SocialLink>, String> {
 }
 
 #[tauri::command]
-async fn get_backgrounds() -> Result<Vec<Background>, String> {
+async fn get_backgrounds(store: State<'_, ManifestStore>) -> Result<Vec<Background>, String> {
   // Try to get from local manifest first
-  if let Some(local_manifest) = load_local_manifest() {
+  if let Some(local_manifest) = store.manifest() {
     let backgrounds: Vec<Background> = local_manifest.backgrounds.values().cloned().collect();
     return Ok(backgrounds);
   }
@@ -485,32 +892,27 @@ async fn get_backgrounds() -> Result<Vec<Background>, String> {
 }
 
 #[tauri::command]
-async fn check_game_updates(game_id: String, current_version: String) -> Result<UpdateInfo, String> {
-    if !check_network().await {
+async fn check_game_updates(app: AppHandle, store: State<'_, ManifestStore>, game_id: String, current_version: String) -> Result<UpdateInfo, String> {
+    if !check_network(&app, &store).await {
         return Err("No internet connection".to_string());
     }
 
-    let manifest_url = "https://pub-72a5a57231ae489cb74409bdc120cb93.r2.dev/manifest.json";
-    
-    match reqwest::get(manifest_url).await {
-        Ok(response) => {
-            if response.status().is_success() {
-                match response.json::<GameManifest>().await {
-                    Ok(manifest) => {
-                        if let Some(game) = manifest.games.iter().find(|g| g.id == game_id) {
-                            // Simple version comparison - in production you'd want semantic versioning
-                            let needs_update = game.version != current_version;
-                            return Ok(UpdateInfo {
-                                current_version: current_version.clone(),
-                                latest_version: game.version.clone(),
-                                needs_update,
-                                update_url: game.download_url.clone(),
-                                changelog: game.changelog.clone(),
-                            });
-                        }
-                    }
-                    Err(e) => eprintln!("Failed to parse manifest for update check: {}", e),
-                }
+    match store.refresh(&manifest_sources(&app)).await {
+        Ok(manifest) => {
+            let games = channels::games_for_channel(&manifest, release_channel(&app));
+            if let Some(game) = games.iter().find(|g| g.id == game_id) {
+                let status = UpdateStatus::between(&current_version, &game.version);
+                let needs_update = status == UpdateStatus::UpdateAvailable;
+                let compatibility = compat::check_game(&manifest, game, &launcher_version(&app));
+                return Ok(UpdateInfo {
+                    current_version: current_version.clone(),
+                    latest_version: game.version.clone(),
+                    status,
+                    needs_update,
+                    update_url: game.download_url.clone(),
+                    changelog: game.changelog.clone(),
+                    launcher_update_required: needs_update && !compatibility.compatible,
+                });
             }
         }
         Err(e) => eprintln!("Failed to fetch manifest for update check: {}", e),
@@ -519,77 +921,70 @@ async fn check_game_updates(game_id: String, current_version: String) -> Result<
     Ok(UpdateInfo {
         current_version: current_version.clone(),
         latest_version: current_version,
+        status: UpdateStatus::UpToDate,
         needs_update: false,
         update_url: None,
         changelog: None,
+        launcher_update_required: false,
     })
 }
 
+// Update a game. The installed build is backed up first (up to `settings.max_backups` are kept as
+// rollbacks); once the new version is installed and verified in its own folder, the player's own
+// files are carried over and the old folder is removed. A failed update never touches the old
+// folder, since installs are staged.
 #[tauri::command]
-async fn download_game_update(game_id: String, download_url: String) -> Result<String, String> {
-    let app_dir = tauri::api::path::app_data_dir(&tauri::Config::default())
-        .ok_or("Could not get app data directory")?;
-    
-    let games_dir = app_dir.join("games").join(&game_id);
+async fn download_game_update(app: AppHandle, store: State<'_, ManifestStore>, manager: State<'_, DownloadManager>, game_id: String, download_url: String) -> Result<InstallResult, String> {
+    let game_info = manifest_game(&app, &store, &game_id).await?;
+    let max_backups = store.manifest().map(|m| m.settings.max_backups).unwrap_or(3).max(0) as usize;
+    let game_base_dir = locations::game_base_dir()?;
+    let backups_dir = locations::backup_dir(&game_base_dir, &game_id);
     
     // Create backup of current installation
-    let backup_dir = app_dir.join("backups").join(&game_id).join(format!("backup_{}", chrono::Utc::now().timestamp()));
-    if games_dir.exists() {
-        std::fs::create_dir_all(&backup_dir).map_err(|e| e.to_string())?;
-        copy_dir_recursive(&games_dir, &backup_dir).map_err(|e| e.to_string())?;
+    let installed = locations::current_install(&game_base_dir, &game_info.name, &game_info.version);
+    if let Some(installed) = installed.as_ref().filter(|_| max_backups > 0) {
+        let backup_dir = backups_dir.join(format!("backup_{}", chrono::Utc::now().timestamp()));
+        println!("Backing up {:?} to {:?}", installed.dir, backup_dir);
+        let (source, target) = (installed.dir.clone(), backup_dir);
+        tokio::task::spawn_blocking(move || copy_dir_recursive(&source, &target))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
     }
     
     // Download and extract new version
-    let result = download_game(game_id.clone(), download_url).await;
+    let result = queue_download(&app, &manager, &game_info, &download_url).await;
     
-    // Clean up old backups (keep only last 3)
-    cleanup_old_backups(&app_dir.join("backups").join(&game_id)).map_err(|e| e.to_string())?;
+    if let (Ok(updated), Some(installed)) = (&result, installed) {
+        let new_dir = PathBuf::from(&updated.install_path);
+        if new_dir != installed.dir {
+            let old_dir = installed.dir;
+            let retired = tokio::task::spawn_blocking(move || retire_install(&old_dir, &new_dir))
+                .await
+                .map_err(|e| e.to_string())?;
+            if let Err(e) = retired {
+                eprintln!("Keeping the previous install of {}: {}", game_id, e);
+            }
+        }
+    }
+    
+    let cleanup = tokio::task::spawn_blocking(move || cleanup_old_backups(&backups_dir, max_backups))
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Err(e) = cleanup {
+        eprintln!("Failed to clean up old backups of {}: {}", game_id, e);
+    }
     
     result
 }
 
 #[tauri::command]
-async fn repair_game(game_id: String) -> Result<RepairResult, String> {
-    // Get launcher directory (where the executable is)
-    let launcher_dir = std::env::current_exe()
-        .map_err(|e| e.to_string())?
-        .parent()
-        .ok_or("Could not get launcher directory")?
-        .to_path_buf();
-    
-    let game_base_dir = launcher_dir.join("AntChillGame");
-    
-    if !game_base_dir.exists() {
-        return Ok(RepairResult {
-            success: false,
-            repaired_files: vec![],
-            errors: vec!["Game directory not found".to_string()],
-            message: "Game directory not found".to_string(),
-        });
-    }
-    
-    // Find game folder by scanning for folders that match the game_id pattern
-    let mut game_folder_to_delete = None;
-    
-    for entry in game_base_dir.read_dir().map_err(|e| e.to_string())? {
-        let entry = entry.map_err(|e| e.to_string())?;
-        let path = entry.path();
-        
-        if path.is_dir() {
-            let folder_name = path.file_name()
-                .and_then(|name| name.to_str())
-                .unwrap_or("");
-            
-            // Check if this folder belongs to the game (e.g., broto.v001 for brato_io)
-            if folder_name.contains(&game_id) || folder_name.starts_with(&game_id.replace("_", "")) {
-                game_folder_to_delete = Some(path);
-                break;
-            }
-        }
-    }
+async fn repair_game(app: AppHandle, store: State<'_, ManifestStore>, game_id: String) -> Result<RepairResult, String> {
+    let game_info = manifest_game(&app, &store, &game_id).await?;
+    let game_base_dir = locations::game_base_dir()?;
     
-    let game_folder = match game_folder_to_delete {
-        Some(folder) => folder,
+    let game_folder = match locations::current_install(&game_base_dir, &game_info.name, &game_info.version) {
+        Some(install) => install.dir,
         None => {
             return Ok(RepairResult {
                 success: false,
@@ -624,9 +1019,46 @@ async fn repair_game(game_id: String) -> Result<RepairResult, String> {
     }
 }
 
+// Remove every installed build of a game together with its backups and unfinished downloads.
+// Returns the removed paths.
 #[tauri::command]
-async fn check_network_status() -> Result<NetworkStatus, String> {
-    let is_online = check_network().await;
+async fn uninstall_game(app: AppHandle, store: State<'_, ManifestStore>, manager: State<'_, DownloadManager>, game_id: String) -> Result<Vec<String>, String> {
+    if manager.list().iter().any(|job| job.game_id == game_id && !job.state.is_finished()) {
+        return Err("Cancel the download of this game before uninstalling it".to_string());
+    }
+    let game_info = manifest_game(&app, &store, &game_id).await?;
+    let game_base_dir = locations::game_base_dir()?;
+    let mut removed = Vec::new();
+    
+    for install in locations::installs(&game_base_dir, &game_info.name) {
+        std::fs::remove_dir_all(&install.dir).map_err(|e| format!("Failed to remove {:?}: {}", install.dir, e))?;
+        removed.push(install.dir);
+    }
+    let backups_dir = locations::backup_dir(&game_base_dir, &game_id);
+    if backups_dir.exists() {
+        std::fs::remove_dir_all(&backups_dir).map_err(|e| format!("Failed to remove {:?}: {}", backups_dir, e))?;
+        removed.push(backups_dir);
+    }
+    if let Ok(entries) = std::fs::read_dir(game_base_dir.join(locations::DOWNLOADS_DIR_NAME)) {
+        for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+            let is_game_download = path.file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| locations::belongs_to_game(name, &game_info.name))
+                .unwrap_or(false);
+            if is_game_download && path.is_file() {
+                std::fs::remove_file(&path).map_err(|e| format!("Failed to remove {:?}: {}", path, e))?;
+                removed.push(path);
+            }
+        }
+    }
+    
+    println!("Uninstalled {}: removed {} item(s)", game_id, removed.len());
+    Ok(removed.iter().map(|path| path.to_string_lossy().to_string()).collect())
+}
+
+#[tauri::command]
+async fn check_network_status(app: AppHandle, store: State<'_, ManifestStore>) -> Result<NetworkStatus, String> {
+    let is_online = check_network(&app, &store).await;
     let message = if is_online {
         "Connected to internet".to_string()
     } else {
@@ -636,6 +1068,82 @@ async fn check_network_status() -> Result<NetworkStatus, String> {
     Ok(NetworkStatus { is_online, message })
 }
 
+#[tauri::command]
+fn get_manifest_sources(app: AppHandle, store: State<'_, ManifestStore>) -> Result<ManifestSourcesInfo, String> {
+    let (sources, from_env) = source::resolve_sources(&load_settings(&app));
+    Ok(ManifestSourcesInfo {
+        sources,
+        from_env,
+        active_source: store.source_url(),
+    })
+}
+
+#[tauri::command]
+fn get_release_channel(app: AppHandle) -> Result<ReleaseChannel, String> {
+    Ok(release_channel(&app))
+}
+
+#[tauri::command]
+fn set_release_channel(app: AppHandle, channel: ReleaseChannel) -> Result<(), String> {
+    let mut settings = load_settings(&app);
+    settings.release_channel = channel;
+    save_settings(&app, &settings)
+}
+
+#[tauri::command]
+fn check_launcher_compatibility(app: AppHandle, store: State<'_, ManifestStore>, game_id: Option<String>) -> Result<LauncherCompatibility, String> {
+    let manifest = store.manifest().ok_or("No manifest available")?;
+    let current_version = launcher_version(&app);
+
+    match game_id {
+        Some(game_id) => {
+            let games = channels::games_for_channel(&manifest, release_channel(&app));
+            let game = games.iter()
+                .find(|g| g.id == game_id)
+                .ok_or("Game not found in manifest")?;
+            Ok(compat::check_game(&manifest, game, &current_version))
+        }
+        None => Ok(compat::check_manifest(&manifest, &current_version)),
+    }
+}
+
+#[tauri::command]
+fn get_manifest_status(store: State<'_, ManifestStore>) -> Result<ManifestStatus, String> {
+    Ok(store.status())
+}
+
+#[tauri::command]
+fn set_manifest_sources(app: AppHandle, sources: Vec<String>) -> Result<(), String> {
+    for url in &sources {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(format!("Invalid manifest URL: {}", url));
+        }
+    }
+
+    let mut settings = load_settings(&app);
+    settings.manifest_sources = sources;
+    save_settings(&app, &settings)
+}
+
+#[tauri::command]
+async fn validate_manifest(app: AppHandle, source: Option<String>) -> Result<ValidationReport, String> {
+    // Validate a local file, a URL, or the first configured mirror
+    let location = match source {
+        Some(location) => location,
+        None => manifest_sources(&app).into_iter().next().ok_or("No manifest sources configured")?,
+    };
+
+    let body = if location.starts_with("http://") || location.starts_with("https://") {
+        source::fetch_bytes(&location, source::FETCH_TIMEOUT)
+            .await?
+            .ok_or(format!("Manifest not found: {}", location))?
+    } else {
+        std::fs::read(&location).map_err(|e| format!("Failed to read {}: {}", location, e))?
+    };
+
+    Ok(validate::validate_manifest_bytes(&body))
+}
+
 #[tauri::command]
 fn toggle_startup_with_windows(enable: bool) -> Result<(), String> {
     set_startup_with_windows(enable)
@@ -682,23 +1190,8 @@ async fn start_dragging(app: tauri::AppHandle) -> Result<(), String> {
 async fn scan_local_games(games: Vec<GameInfo>) -> Result<Vec<GameInfo>, String> {
     let mut scanned_games = games;
     
-    // Get launcher directory - go up to the project root
-    let launcher_dir = std::env::current_exe()
-        .map_err(|e| format!("Failed to get launcher path: {}", e))?
-        .parent()
-        .ok_or("Failed to get launcher directory")?
-        .to_path_buf();
-    
     // Look for AntChillGame directory next to launcher executable
-    let launcher_exe_dir = std::env::current_exe()
-        .map_err(|e| e.to_string())?
-        .parent()
-        .ok_or("Could not get launcher directory")?
-        .to_path_buf();
-    let game_base_dir = launcher_exe_dir.join("AntChillGame");
-    
-    println!("Launcher directory: {:?}", launcher_dir);
-    println!("Launcher exe directory: {:?}", launcher_exe_dir);
+    let game_base_dir = locations::game_base_dir()?;
     println!("Looking for game base directory: {:?}", game_base_dir);
     
     if !game_base_dir.exists() {
@@ -711,37 +1204,18 @@ async fn scan_local_games(games: Vec<GameInfo>) -> Result<Vec<GameInfo>, String>
     
     for game in &mut scanned_games {
         println!("Scanning game: {} v{}", game.name, game.version);
+        // Installs of this build are blocked until the launcher itself is updated
+        let launcher_blocked = game.status == compat::LAUNCHER_UPDATE_REQUIRED;
         
         if game.status == "coming_soon" {
             println!("Skipping coming soon game: {}", game.name);
             continue; // Skip coming soon games
         }
         
-        // Look for game directory with pattern: [tên game].[phiên bản]
-        let game_name_lower = game.name.to_lowercase();
-        let possible_patterns = vec![
-            format!("{}.v{}", game_name_lower, game.version),
-            format!("{}.{}", game_name_lower, game.version),
-            format!("{}.v{}", game.name, game.version),
-            format!("{}.{}", game.name, game.version),
-            format!("{}.v{}", game_name_lower.to_uppercase(), game.version),
-            format!("{}.{}", game_name_lower.to_uppercase(), game.version),
-            // Fallback patterns
-            format!("{}/{}", game.id, format!("{}.v{}", game_name_lower, game.version)),
-            format!("{}/{}", game.id, format!("{}.{}", game_name_lower, game.version)),
-            game_name_lower.clone(),
-            game.name.clone(),
-        ];
+        game.installed_version = locations::current_install(&game_base_dir, &game.name, &game.version).map(|install| install.version);
         
-        let mut game_dir = None;
-        for pattern in &possible_patterns {
-            let test_dir = game_base_dir.join(pattern);
-            println!("Looking for game directory: {:?}", test_dir);
-            if test_dir.exists() {
-                game_dir = Some(test_dir);
-                break;
-            }
-        }
+        // Look for game directory with pattern: [tên game].[phiên bản]
+        let game_dir = locations::find_install(&game_base_dir, &game.name, &game.version).map(|install| install.dir);
         
         if let Some(found_dir) = game_dir {
             println!("Game directory found: {:?}", found_dir);
@@ -760,11 +1234,15 @@ async fn scan_local_games(games: Vec<GameInfo>) -> Result<Vec<GameInfo>, String>
             if let Some(older_version) = find_older_version(&game_base_dir, &game.name)? {
                 println!("Older version found: {}", older_version);
                 game.executable_path = Some(older_version);
-                game.status = "update_available".to_string();
+                if !launcher_blocked {
+                    game.status = "update_available".to_string();
+                }
             } else {
                 println!("No game installation found");
                 game.executable_path = None;
-                game.status = "available".to_string();
+                if !launcher_blocked {
+                    game.status = "available".to_string();
+                }
             }
         }
         
@@ -860,23 +1338,11 @@ fn find_executable_in_directory(dir: &std::path::Path) -> Result<Option<String>,
     Ok(None)
 }
 
+// Executable of the newest other installed build of a game
 fn find_older_version(base_dir: &std::path::Path, game_name: &str) -> Result<Option<String>, String> {
-    if let Ok(entries) = std::fs::read_dir(base_dir) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if path.is_dir() {
-                    if let Some(dir_name) = path.file_name() {
-                        let dir_name_str = dir_name.to_string_lossy();
-                        if dir_name_str.starts_with(game_name) {
-                            // Found a version of this game
-                            if let Some(exec_path) = find_executable_in_directory(&path)? {
-                                return Ok(Some(exec_path));
-                            }
-                        }
-                    }
-                }
-            }
+    for install in locations::installs(base_dir, game_name).iter().rev() {
+        if let Some(exec_path) = find_executable_in_directory(&install.dir)? {
+            return Ok(Some(exec_path));
         }
     }
     Ok(None)
@@ -927,29 +1393,65 @@ fn copy_dir_recursive(src: &PathBuf, dst: &PathBuf) -> Result<(), std::io::Error
     Ok(())
 }
 
-fn cleanup_old_backups(backup_dir: &PathBuf) -> Result<(), std::io::Error> {
+// Copy files the player added to an install (saves, settings) into the folder replacing it:
+// anything the new install lacks that the old build's file manifest does not list. Without a
+// stored file manifest every missing file counts as the player's.
+fn carry_over_player_files(old_dir: &Path, new_dir: &Path, listed: &HashSet<String>, relative: &Path) -> Result<(), std::io::Error> {
+    for entry in std::fs::read_dir(old_dir.join(relative))? {
+        let entry = entry?;
+        let path = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            carry_over_player_files(old_dir, new_dir, listed, &path)?;
+            continue;
+        }
+        let key = path.to_string_lossy().replace('\\', "/").to_lowercase();
+        let target = new_dir.join(&path);
+        if target.exists() || listed.contains(&key) || key == files::INSTALLED_FILE_MANIFEST {
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(entry.path(), &target)?;
+    }
+    Ok(())
+}
+
+// Remove an install that an update replaced with another folder, keeping the player's own files.
+// Nothing is removed unless they were all copied.
+fn retire_install(old_dir: &Path, new_dir: &Path) -> Result<(), String> {
+    let listed: HashSet<String> = files::load_installed(old_dir)
+        .map(|manifest| manifest.files.iter().map(|f| f.path.to_lowercase()).collect())
+        .unwrap_or_default();
+    carry_over_player_files(old_dir, new_dir, &listed, Path::new(""))
+        .map_err(|e| format!("Failed to carry over player files: {}", e))?;
+    std::fs::remove_dir_all(old_dir).map_err(|e| format!("Failed to remove {:?}: {}", old_dir, e))?;
+    println!("Removed previous install {:?}", old_dir);
+    Ok(())
+}
+
+// Keep the newest `keep` backups of a game. Backups are named backup_<timestamp>, which orders
+// them on every filesystem; anything else in the folder is left alone.
+fn cleanup_old_backups(backup_dir: &Path, keep: usize) -> Result<(), std::io::Error> {
     if !backup_dir.exists() {
         return Ok(());
     }
     
-    let mut entries: Vec<_> = std::fs::read_dir(backup_dir)?
+    let mut backups: Vec<(i64, PathBuf)> = std::fs::read_dir(backup_dir)?
         .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let timestamp = entry.file_name().to_str()?.strip_prefix("backup_")?.parse::<i64>().ok()?;
+            Some((timestamp, entry.path()))
+        })
         .collect();
     
-    // Sort by creation time (newest first)
-    entries.sort_by(|a, b| {
-        a.metadata().unwrap().created().unwrap_or(std::time::SystemTime::UNIX_EPOCH)
-            .cmp(&b.metadata().unwrap().created().unwrap_or(std::time::SystemTime::UNIX_EPOCH))
-    });
-    
-    // Remove old backups, keep only last 3
-    if entries.len() > 3 {
-        for entry in entries[..entries.len() - 3].iter() {
-            if entry.file_type()?.is_dir() {
-                std::fs::remove_dir_all(&entry.path())?;
-            } else {
-                std::fs::remove_file(&entry.path())?;
-            }
+    // Newest first
+    backups.sort_by_key(|(timestamp, _)| std::cmp::Reverse(*timestamp));
+    for (_, path) in backups.iter().skip(keep) {
+        if path.is_dir() {
+            std::fs::remove_dir_all(path)?;
+        } else {
+            std::fs::remove_file(path)?;
         }
     }
     
@@ -994,8 +1496,57 @@ fn main() {
     tauri::Builder::default()
         .system_tray(create_system_tray())
         .on_system_tray_event(handle_system_tray_event)
+        .setup(|app| {
+            // Hydrate the manifest cache so offline mode works right after a restart
+            let manifest_path = get_manifest_path(&app.handle())?;
+            let app_data_dir = app.path_resolver().app_data_dir();
+            let override_locations = overrides::override_locations(app_data_dir.as_deref());
+            let handle = app.handle();
+            let store = ManifestStore::load(manifest_path, override_locations)
+                .with_change_listener(move |previous, current| {
+                    // Tell the frontend what is new so it can badge games instead of re-rendering blindly
+                    let changes: Vec<ManifestChange> = diff::diff_manifests(previous, current, release_channel(&handle));
+                    if changes.is_empty() {
+                        return;
+                    }
+                    println!("Manifest changed: {} change(s)", changes.len());
+                    if let Err(e) = handle.emit_all(diff::MANIFEST_CHANGED_EVENT, &changes) {
+                        eprintln!("Failed to emit manifest changes: {}", e);
+                    }
+                });
+            store.set_override_enabled(load_settings(&app.handle()).manifest_override_enabled);
+            app.manage(store);
+            let downloads = DownloadManager::new();
+            downloads.apply_limits(&load_settings(&app.handle()));
+            app.manage(downloads);
+            DownloadManager::start_window_timer(app.handle());
+
+            // Installs interrupted by a crash or shutdown never reached their game folder
+            match locations::game_base_dir() {
+                Ok(game_base_dir) => staging::cleanup_leftovers(&game_base_dir),
+                Err(e) => eprintln!("Failed to locate games folder: {}", e),
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             download_game,
+            list_downloads,
+            pause_download,
+            resume_download,
+            cancel_download,
+            move_download,
+            get_max_concurrent_downloads,
+            set_max_concurrent_downloads,
+            get_download_segments,
+            set_download_segments,
+            get_download_speed_limit,
+            set_download_speed_limit,
+            get_download_window,
+            set_download_window,
+            get_extraction_mode,
+            set_extraction_mode,
+            get_manifest_override_enabled,
+            set_manifest_override_enabled,
             launch_game,
             get_games,
             get_offline_games,
@@ -1004,7 +1555,16 @@ fn main() {
             check_game_updates,
             download_game_update,
             repair_game,
+            uninstall_game,
+            verify_game_files,
             check_network_status,
+            get_manifest_sources,
+            get_manifest_status,
+            validate_manifest,
+            get_release_channel,
+            set_release_channel,
+            check_launcher_compatibility,
+            set_manifest_sources,
             toggle_startup_with_windows,
             get_startup_status,
             minimize_window,
@@ -0,0 +1,326 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use super::{overrides, schema, signature};
+use super::source::{self, CacheValidators, FetchOutcome, FetchedManifest};
+use crate::GameManifest;
+
+// Refreshes within this window reuse the previous result instead of hitting the network
+const REFRESH_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LocalManifest {
+  pub manifest: GameManifest,
+  // Manifest as the mirror served it and its signature (base64). `manifest` is rebuilt from these
+  // on load, so a cache file edited on disk is never trusted.
+  #[serde(default)]
+  pub body: Option<String>,
+  #[serde(default)]
+  pub signature: Option<String>,
+  pub last_updated: u64,
+  pub is_offline: bool,
+  #[serde(default)]
+  pub source_url: Option<String>,
+  #[serde(default)]
+  pub etag: Option<String>,
+  #[serde(default)]
+  pub last_modified: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ManifestStatus {
+  pub available: bool,
+  pub last_updated: Option<u64>,
+  pub age_seconds: Option<u64>,
+  pub is_offline: bool,
+  pub source_url: Option<String>,
+  pub last_error: Option<String>,
+  pub override_active: bool,
+  pub override_path: Option<String>,
+  pub override_error: Option<String>,
+}
+
+// Called with the previous and the freshly fetched manifest (override applied to both)
+type ChangeListener = Box<dyn Fn(&GameManifest, &GameManifest) + Send + Sync>;
+
+// Last verified manifest, kept in managed state and mirrored to local_manifest.json
+pub struct ManifestStore {
+  path: PathBuf,
+  override_locations: Vec<PathBuf>,
+  // The override is unsigned, so it is only read once the player opted in through the settings
+  override_enabled: AtomicBool,
+  local: RwLock<Option<LocalManifest>>,
+  last_error: RwLock<Option<String>>,
+  // Held while a refresh is in flight; remembers when the last one finished and its error
+  last_refresh: tokio::sync::Mutex<Option<(Instant, Option<String>)>>,
+  change_listener: Option<ChangeListener>,
+}
+
+// Check the cached manifest's signature again and rebuild the manifest from the signed bytes
+fn verify_local(mut local: LocalManifest) -> Result<LocalManifest, String> {
+  let body = local.body.as_deref().ok_or("Cached manifest has no signed copy")?;
+  match &local.signature {
+    Some(sig) => {
+      let sig = BASE64.decode(sig).map_err(|e| format!("Invalid cached signature: {}", e))?;
+      signature::verify_manifest(body.as_bytes(), &sig)?;
+    }
+    None if signature::allow_unsigned() => {}
+    None => return Err("Cached manifest is not signed".to_string()),
+  }
+  local.manifest = schema::parse_manifest(body.as_bytes())?;
+  Ok(local)
+}
+
+fn now_secs() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or(0)
+}
+
+impl ManifestStore {
+  // Hydrate from disk; a manifest loaded at startup counts as offline until a fetch succeeds
+  pub fn load(path: PathBuf, override_locations: Vec<PathBuf>) -> Self {
+    let local = match fs::read_to_string(&path) {
+      Ok(contents) => match serde_json::from_str::<LocalManifest>(&contents).map_err(|e| e.to_string()).and_then(verify_local) {
+        Ok(mut local) => {
+          println!("Loaded local manifest from {:?} ({} games)", path, local.manifest.games.len());
+          local.is_offline = true;
+          Some(local)
+        }
+        Err(e) => {
+          eprintln!("Dropping local manifest {:?}: {}", path, e);
+          None
+        }
+      },
+      Err(_) => None,
+    };
+
+    ManifestStore {
+      path,
+      override_locations,
+      override_enabled: AtomicBool::new(false),
+      local: RwLock::new(local),
+      last_error: RwLock::new(None),
+      last_refresh: tokio::sync::Mutex::new(None),
+      change_listener: None,
+    }
+  }
+
+  pub fn with_change_listener<F>(mut self, listener: F) -> Self
+  where
+    F: Fn(&GameManifest, &GameManifest) + Send + Sync + 'static,
+  {
+    self.change_listener = Some(Box::new(listener));
+    self
+  }
+
+  // Cached manifest with the developer override applied, if one is present
+  pub fn manifest(&self) -> Option<GameManifest> {
+    self.remote_manifest().map(|manifest| self.with_override(manifest))
+  }
+
+  // Cached manifest exactly as the mirror published it
+  pub fn remote_manifest(&self) -> Option<GameManifest> {
+    self.local.read().ok()?.as_ref().map(|lm| lm.manifest.clone())
+  }
+
+  pub fn set_override_enabled(&self, enabled: bool) {
+    if enabled {
+      eprintln!("Manifest overrides are enabled, {} is applied without a signature", overrides::OVERRIDE_FILE_NAME);
+    }
+    self.override_enabled.store(enabled, Ordering::SeqCst);
+  }
+
+  pub fn override_path(&self) -> Option<PathBuf> {
+    if !self.override_enabled.load(Ordering::SeqCst) {
+      return None;
+    }
+    overrides::find_override(&self.override_locations)
+  }
+
+  // The override file and `manifest` merged with it. The file is re-read on every access so
+  // edits apply without a restart.
+  fn merge_override(&self, manifest: &GameManifest) -> Option<(PathBuf, Result<GameManifest, String>)> {
+    let path = self.override_path()?;
+    let merged = overrides::apply_override(manifest, &path);
+    Some((path, merged))
+  }
+
+  // An override is active only when it is enabled, present and merges cleanly; the same check
+  // decides what manifest() serves and what status() reports
+  pub fn override_active(&self) -> bool {
+    self.remote_manifest()
+      .and_then(|manifest| self.merge_override(&manifest))
+      .map(|(_, merged)| merged.is_ok())
+      .unwrap_or(false)
+  }
+
+  fn with_override(&self, manifest: GameManifest) -> GameManifest {
+    match self.merge_override(&manifest) {
+      Some((_, Ok(merged))) => merged,
+      Some((_, Err(e))) => {
+        eprintln!("Ignoring manifest override: {}", e);
+        manifest
+      }
+      None => manifest,
+    }
+  }
+
+  pub fn source_url(&self) -> Option<String> {
+    self.local.read().ok()?.as_ref().and_then(|lm| lm.source_url.clone())
+  }
+
+  fn validators(&self) -> Option<CacheValidators> {
+    let local = self.local.read().ok()?;
+    let lm = local.as_ref()?;
+    Some(CacheValidators {
+      source_url: lm.source_url.clone()?,
+      etag: lm.etag.clone(),
+      last_modified: lm.last_modified.clone(),
+    })
+  }
+
+  // Save manifest to local storage
+  pub fn save(&self, fetched: &FetchedManifest) -> Result<(), String> {
+    let body = String::from_utf8(fetched.body.clone()).map_err(|e| e.to_string())?;
+    self.write(LocalManifest {
+      manifest: fetched.manifest.clone(),
+      body: Some(body),
+      signature: fetched.signature.as_ref().map(|sig| BASE64.encode(sig)),
+      last_updated: now_secs(),
+      is_offline: false,
+      source_url: Some(fetched.source_url.clone()),
+      etag: fetched.etag.clone(),
+      last_modified: fetched.last_modified.clone(),
+    })
+  }
+
+  // The mirror confirmed the cached manifest is current
+  fn touch(&self, source_url: &str) -> Result<GameManifest, String> {
+    let mut local_manifest = self.local.read()
+      .map_err(|e| e.to_string())?
+      .clone()
+      .ok_or("Mirror answered 304 but no manifest is cached")?;
+    local_manifest.last_updated = now_secs();
+    local_manifest.is_offline = false;
+    local_manifest.source_url = Some(source_url.to_string());
+
+    let manifest = local_manifest.manifest.clone();
+    self.write(local_manifest)?;
+    Ok(manifest)
+  }
+
+  fn write(&self, local_manifest: LocalManifest) -> Result<(), String> {
+    // Save to file for persistence
+    if let Some(parent) = self.path.parent() {
+      fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let manifest_json = serde_json::to_string_pretty(&local_manifest)
+      .map_err(|e| e.to_string())?;
+    fs::write(&self.path, manifest_json).map_err(|e| e.to_string())?;
+
+    let mut local = self.local.write().map_err(|e| e.to_string())?;
+    *local = Some(local_manifest);
+    if let Ok(mut last_error) = self.last_error.write() {
+      *last_error = None;
+    }
+    Ok(())
+  }
+
+  // Fetch the manifest with conditional headers. Concurrent callers wait for the
+  // request already in flight and reuse its result for REFRESH_TTL.
+  pub async fn refresh(&self, sources: &[String]) -> Result<GameManifest, String> {
+    let mut last_refresh = self.last_refresh.lock().await;
+    if let Some((at, error)) = last_refresh.as_ref() {
+      if at.elapsed() < REFRESH_TTL {
+        match error {
+          Some(e) => return Err(e.clone()),
+          None => {
+            if let Some(manifest) = self.manifest() {
+              return Ok(manifest);
+            }
+          }
+        }
+      }
+    }
+
+    let result = match source::fetch_manifest(sources, self.validators().as_ref()).await {
+      Ok(FetchOutcome::Fresh(fetched)) => {
+        println!("Successfully loaded online manifest with {} games", fetched.manifest.games.len());
+        let previous = self.manifest();
+        if let Err(e) = self.save(&fetched) {
+          eprintln!("Failed to save local manifest: {}", e);
+        }
+
+        let manifest = self.with_override(fetched.manifest);
+        if let (Some(previous), Some(listener)) = (previous, &self.change_listener) {
+          listener(&previous, &manifest);
+        }
+        Ok(manifest)
+      }
+      Ok(FetchOutcome::NotModified { source_url }) => {
+        println!("Manifest not modified on {}, using cached copy", source_url);
+        self.touch(&source_url).map(|manifest| self.with_override(manifest))
+      }
+      Err(e) => Err(e),
+    };
+
+    if let Err(e) = &result {
+      self.mark_offline(e);
+    }
+    *last_refresh = Some((Instant::now(), result.as_ref().err().cloned()));
+    result
+  }
+
+  // Flag that the cached manifest is being served because the online one could not be used
+  pub fn mark_offline(&self, reason: &str) {
+    if let Ok(mut local) = self.local.write() {
+      if let Some(lm) = local.as_mut() {
+        lm.is_offline = true;
+      }
+    }
+    if let Ok(mut last_error) = self.last_error.write() {
+      *last_error = Some(reason.to_string());
+    }
+  }
+
+  pub fn status(&self) -> ManifestStatus {
+    let local = self.local.read().ok().and_then(|l| l.clone());
+    let last_error = self.last_error.read().ok().and_then(|e| e.clone());
+    let merged = local.as_ref().and_then(|lm| self.merge_override(&lm.manifest));
+    let override_active = matches!(merged, Some((_, Ok(_))));
+    let override_error = merged.as_ref().and_then(|(_, result)| result.as_ref().err().cloned());
+    let override_path = self.override_path().map(|p| p.to_string_lossy().to_string());
+    match local {
+      Some(lm) => ManifestStatus {
+        available: true,
+        last_updated: Some(lm.last_updated),
+        age_seconds: Some(now_secs().saturating_sub(lm.last_updated)),
+        is_offline: lm.is_offline,
+        source_url: lm.source_url,
+        last_error,
+        override_active,
+        override_path,
+        override_error,
+      },
+      None => ManifestStatus {
+        available: false,
+        last_updated: None,
+        age_seconds: None,
+        is_offline: true,
+        source_url: None,
+        last_error,
+        override_active,
+        override_path,
+        override_error,
+      },
+    }
+  }
+}
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+use crate::version::compare_versions;
+use crate::{GameInfo, GameManifest, GameVersion};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseChannel {
+  Stable,
+  Beta,
+  Internal,
+}
+
+impl Default for ReleaseChannel {
+  fn default() -> Self {
+    ReleaseChannel::Stable
+  }
+}
+
+impl ReleaseChannel {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      ReleaseChannel::Stable => "stable",
+      ReleaseChannel::Beta => "beta",
+      ReleaseChannel::Internal => "internal",
+    }
+  }
+
+  // Beta also sees stable builds, internal sees everything
+  pub fn can_see(self, build: ReleaseChannel) -> bool {
+    build <= self
+  }
+}
+
+fn apply_version(game: &GameInfo, version: &GameVersion) -> GameInfo {
+  let mut resolved = game.clone();
+  resolved.version = version.version.clone();
+  resolved.channel = version.channel;
+  resolved.download_url = version.download_url.clone();
+  resolved.file_size = version.file_size.clone();
+  resolved.release_date = version.release_date.clone();
+  resolved.changelog = version.changelog.clone();
+  resolved.min_launcher_version = version.min_launcher_version.clone();
+  resolved.sha256 = version.sha256.clone();
+  resolved.size = version.size;
+  resolved.archive_format = version.archive_format.clone();
+  resolved.files_url = version.files_url.clone();
+  resolved.files_sha256 = version.files_sha256.clone();
+  resolved
+}
+
+// Newest build of a game that the selected channel is allowed to see. The top-level
+// fields describe a build too (stable unless `channel` says otherwise).
+pub fn resolve_game(game: &GameInfo, channel: ReleaseChannel) -> Option<GameInfo> {
+  let newest = game.versions.iter()
+    .filter(|v| channel.can_see(v.channel))
+    .max_by(|a, b| compare_versions(&a.version, &b.version));
+
+  let top_level_visible = channel.can_see(game.channel);
+  match newest {
+    Some(version) if !top_level_visible || compare_versions(&version.version, &game.version).is_gt() => {
+      Some(apply_version(game, version))
+    }
+    _ if top_level_visible => Some(game.clone()),
+    _ => None,
+  }
+}
+
+// Games list as the selected channel sees it; games with no visible build are hidden
+pub fn games_for_channel(manifest: &GameManifest, channel: ReleaseChannel) -> Vec<GameInfo> {
+  manifest.games.iter()
+    .filter_map(|game| resolve_game(game, channel))
+    .collect()
+}
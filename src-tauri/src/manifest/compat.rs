@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+use crate::version::compare_versions;
+use crate::{GameInfo, GameManifest};
+
+// Game status shown when the running launcher is too old to install the build
+pub const LAUNCHER_UPDATE_REQUIRED: &str = "launcher_update_required";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LauncherCompatibility {
+  pub compatible: bool,
+  pub current_version: String,
+  pub required_version: Option<String>,
+  pub update_url: Option<String>,
+  pub message: Option<String>,
+}
+
+fn newest<'a>(a: Option<&'a str>, b: Option<&'a str>) -> Option<&'a str> {
+  match (a, b) {
+    (Some(a), Some(b)) => Some(if compare_versions(a, b).is_ge() { a } else { b }),
+    (a, b) => a.or(b),
+  }
+}
+
+fn check(manifest: &GameManifest, required: Option<&str>, current_version: &str, subject: &str) -> LauncherCompatibility {
+  let compatible = required
+    .map(|required| compare_versions(current_version, required).is_ge())
+    .unwrap_or(true);
+  let update_url = Some(manifest.launcher_config.update_url.clone()).filter(|url| !url.is_empty());
+  let message = if compatible {
+    None
+  } else {
+    Some(format!(
+      "{} requires launcher {} or newer (running {}), please update the launcher",
+      subject, required.unwrap_or_default(), current_version
+    ))
+  };
+
+  LauncherCompatibility {
+    compatible,
+    current_version: current_version.to_string(),
+    required_version: required.map(|r| r.to_string()),
+    update_url: if compatible { None } else { update_url },
+    message,
+  }
+}
+
+pub fn check_manifest(manifest: &GameManifest, current_version: &str) -> LauncherCompatibility {
+  check(manifest, manifest.min_launcher_version.as_deref(), current_version, "This manifest")
+}
+
+// The stricter of the manifest-wide and per-build requirement applies
+pub fn check_game(manifest: &GameManifest, game: &GameInfo, current_version: &str) -> LauncherCompatibility {
+  let required = newest(manifest.min_launcher_version.as_deref(), game.min_launcher_version.as_deref());
+  check(manifest, required, current_version, &game.name)
+}
+
+// Flag resolved games this launcher cannot install
+pub fn mark_incompatible_games(manifest: &GameManifest, games: &mut [GameInfo], current_version: &str) {
+  for game in games.iter_mut() {
+    if game.is_coming_soon {
+      continue;
+    }
+    if !check_game(manifest, game, current_version).compatible {
+      game.status = LAUNCHER_UPDATE_REQUIRED.to_string();
+    }
+  }
+}
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+use super::channels::{self, ReleaseChannel};
+use crate::version::compare_versions;
+use crate::GameManifest;
+
+// Emitted to the frontend with the list of changes after a manifest refresh
+pub const MANIFEST_CHANGED_EVENT: &str = "manifest-changed";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ManifestChange {
+  GameAdded {
+    game_id: String,
+    name: String,
+    version: String,
+    is_coming_soon: bool,
+  },
+  GameVersionBumped {
+    game_id: String,
+    name: String,
+    old_version: String,
+    new_version: String,
+    changelog: Option<String>,
+  },
+  GameReleased {
+    game_id: String,
+    name: String,
+    version: String,
+  },
+  BackgroundChanged {
+    background_id: String,
+    old_image_url: Option<String>,
+    new_image_url: String,
+  },
+  SocialLinkAdded {
+    link_id: String,
+    url: String,
+    tooltip: String,
+  },
+}
+
+// Changes between two manifests as a player on the given channel would see them
+pub fn diff_manifests(old: &GameManifest, new: &GameManifest, channel: ReleaseChannel) -> Vec<ManifestChange> {
+  let mut changes = Vec::new();
+  let old_games = channels::games_for_channel(old, channel);
+  let new_games = channels::games_for_channel(new, channel);
+
+  for game in &new_games {
+    match old_games.iter().find(|g| g.id == game.id) {
+      None => changes.push(ManifestChange::GameAdded {
+        game_id: game.id.clone(),
+        name: game.name.clone(),
+        version: game.version.clone(),
+        is_coming_soon: game.is_coming_soon,
+      }),
+      Some(previous) => {
+        if previous.is_coming_soon && !game.is_coming_soon {
+          changes.push(ManifestChange::GameReleased {
+            game_id: game.id.clone(),
+            name: game.name.clone(),
+            version: game.version.clone(),
+          });
+        } else if compare_versions(&game.version, &previous.version).is_gt() {
+          changes.push(ManifestChange::GameVersionBumped {
+            game_id: game.id.clone(),
+            name: game.name.clone(),
+            old_version: previous.version.clone(),
+            new_version: game.version.clone(),
+            changelog: game.changelog.clone(),
+          });
+        }
+      }
+    }
+  }
+
+  let mut background_ids: Vec<&String> = new.backgrounds.keys().collect();
+  background_ids.sort();
+  for id in background_ids {
+    let background = &new.backgrounds[id];
+    let previous = old.backgrounds.get(id);
+    if previous.map(|bg| &bg.image_url) != Some(&background.image_url) {
+      changes.push(ManifestChange::BackgroundChanged {
+        background_id: id.clone(),
+        old_image_url: previous.map(|bg| bg.image_url.clone()),
+        new_image_url: background.image_url.clone(),
+      });
+    }
+  }
+
+  for link in &new.social_links {
+    if !old.social_links.iter().any(|l| l.id == link.id) {
+      changes.push(ManifestChange::SocialLinkAdded {
+        link_id: link.id.clone(),
+        url: link.url.clone(),
+        tooltip: link.tooltip.clone(),
+      });
+    }
+  }
+
+  changes
+}
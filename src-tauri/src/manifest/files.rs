@@ -0,0 +1,177 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::source::{self, FETCH_TIMEOUT};
+use crate::download::extract::enclosed_path;
+use crate::download::integrity::hash_file_into;
+
+// Stored in the root of every install that came with a file manifest
+pub const INSTALLED_FILE_MANIFEST: &str = ".antchill-files.json";
+
+// One file of a game build, `path` relative to the install folder with "/" separators
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FileEntry {
+  pub path: String,
+  pub size: u64,
+  pub sha256: String,
+  #[serde(default)]
+  pub executable: bool,
+  // bsdiff patches that turn an earlier version of this file into this one
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub patches: Vec<FilePatch>,
+}
+
+// A bsdiff patch applying to the earlier content with hash `from_sha256`. `url` is absolute or
+// relative to the file manifest's `base_url`; `size` and `sha256` describe the patch file.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FilePatch {
+  pub from_sha256: String,
+  pub url: String,
+  pub size: u64,
+  pub sha256: String,
+}
+
+// Every file of one game version, published next to its archive (`files_url` in the manifest)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileManifest {
+  pub version: String,
+  pub files: Vec<FileEntry>,
+  // Files are also served one by one from `<base_url>/<path>`, which allows delta updates
+  #[serde(default)]
+  pub base_url: Option<String>,
+}
+
+// Differences between an install folder and its file manifest
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FileCheckReport {
+  pub checked: usize,
+  pub missing: Vec<String>,
+  // Present but with a different size or hash, e.g. edited by the player
+  pub modified: Vec<String>,
+}
+
+impl FileCheckReport {
+  pub fn is_clean(&self) -> bool {
+    self.missing.is_empty() && self.modified.is_empty()
+  }
+}
+
+pub fn is_sha256_hex(value: &str) -> bool {
+  value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl FileManifest {
+  // Reject entries that could not have come from a safe extraction
+  pub fn validate(&self) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for entry in &self.files {
+      if enclosed_path(&entry.path).is_none() {
+        return Err(format!("Unsafe path in file manifest: {}", entry.path));
+      }
+      if !is_sha256_hex(&entry.sha256) {
+        return Err(format!("Invalid sha256 for {}: {}", entry.path, entry.sha256));
+      }
+      if entry.patches.iter().any(|p| !is_sha256_hex(&p.from_sha256) || !is_sha256_hex(&p.sha256)) {
+        return Err(format!("Invalid patch hash for {}", entry.path));
+      }
+      if !seen.insert(entry.path.to_ascii_lowercase()) {
+        return Err(format!("Duplicate path in file manifest: {}", entry.path));
+      }
+    }
+    Ok(())
+  }
+}
+
+// Download a game's file manifest, checking it against the hash pinned in the signed manifest.
+// It names the files, hashes and patch URLs an install trusts, so there is no unpinned variant.
+pub async fn fetch(url: &str, expected_sha256: &str) -> Result<FileManifest, String> {
+  let body = source::fetch_bytes(url, FETCH_TIMEOUT)
+    .await
+    .map_err(|e| format!("Failed to fetch file manifest {}: {}", url, e))?
+    .ok_or_else(|| format!("File manifest not found: {}", url))?;
+
+  let actual = hex::encode(Sha256::digest(&body));
+  if !actual.eq_ignore_ascii_case(expected_sha256.trim()) {
+    return Err(format!("File manifest SHA-256 mismatch: expected {}, got {}", expected_sha256, actual));
+  }
+
+  let mut manifest: FileManifest = serde_json::from_slice(&body)
+    .map_err(|e| format!("Invalid file manifest: {}", e))?;
+  for entry in manifest.files.iter_mut() {
+    entry.sha256 = entry.sha256.trim().to_ascii_lowercase();
+    for patch in entry.patches.iter_mut() {
+      patch.from_sha256 = patch.from_sha256.trim().to_ascii_lowercase();
+      patch.sha256 = patch.sha256.trim().to_ascii_lowercase();
+    }
+  }
+  manifest.validate()?;
+  Ok(manifest)
+}
+
+// Does the file on disk match its entry? Sizes are compared first so most changes skip hashing.
+pub fn file_matches(path: &Path, entry: &FileEntry) -> Result<bool, String> {
+  match fs::metadata(path) {
+    Ok(meta) if meta.is_file() && meta.len() == entry.size => {}
+    _ => return Ok(false),
+  }
+  let mut hasher = Sha256::new();
+  hash_file_into(path, &mut hasher)?;
+  Ok(hex::encode(hasher.finalize()) == entry.sha256)
+}
+
+// Compare an install folder against a file manifest. Files that are not listed are ignored
+// (saves, configs, the stored manifest itself).
+pub fn check_files(dir: &Path, manifest: &FileManifest) -> Result<FileCheckReport, String> {
+  let mut report = FileCheckReport::default();
+  for entry in &manifest.files {
+    let relative = enclosed_path(&entry.path).ok_or_else(|| format!("Unsafe path in file manifest: {}", entry.path))?;
+    let path = dir.join(relative);
+    report.checked += 1;
+    if !path.is_file() {
+      report.missing.push(entry.path.clone());
+    } else if !file_matches(&path, entry)? {
+      report.modified.push(entry.path.clone());
+    }
+  }
+  Ok(report)
+}
+
+// Zip and tar entries do not always carry permissions, so the manifest's flag wins
+#[cfg(unix)]
+pub fn apply_permissions(dir: &Path, manifest: &FileManifest) -> Result<(), String> {
+  use std::os::unix::fs::PermissionsExt;
+  for entry in manifest.files.iter().filter(|f| f.executable) {
+    if let Some(relative) = enclosed_path(&entry.path) {
+      let path = dir.join(relative);
+      let mut permissions = fs::metadata(&path).map_err(|e| e.to_string())?.permissions();
+      permissions.set_mode(permissions.mode() | 0o111);
+      fs::set_permissions(&path, permissions).map_err(|e| e.to_string())?;
+    }
+  }
+  Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn apply_permissions(_dir: &Path, _manifest: &FileManifest) -> Result<(), String> {
+  Ok(())
+}
+
+pub fn save_installed(dir: &Path, manifest: &FileManifest) -> Result<(), String> {
+  let json = serde_json::to_string_pretty(manifest).map_err(|e| e.to_string())?;
+  fs::write(dir.join(INSTALLED_FILE_MANIFEST), json).map_err(|e| e.to_string())
+}
+
+// File manifest stored with an install, if it has one
+pub fn load_installed(dir: &Path) -> Option<FileManifest> {
+  let contents = fs::read_to_string(dir.join(INSTALLED_FILE_MANIFEST)).ok()?;
+  match serde_json::from_str(&contents) {
+    Ok(manifest) => Some(manifest),
+    Err(e) => {
+      eprintln!("Ignoring unreadable file manifest in {:?}: {}", dir, e);
+      None
+    }
+  }
+}
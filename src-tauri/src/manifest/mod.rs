@@ -0,0 +1,10 @@
+pub mod cache;
+pub mod channels;
+pub mod compat;
+pub mod diff;
+pub mod files;
+pub mod overrides;
+pub mod schema;
+pub mod signature;
+pub mod source;
+pub mod validate;
@@ -0,0 +1,65 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+
+use crate::GameManifest;
+
+// Developer override merged over the remote manifest, e.g. to add unpublished games
+pub const OVERRIDE_FILE_NAME: &str = "manifest.override.json";
+
+// Next to the launcher executable first, then the app data directory
+pub fn override_locations(app_data_dir: Option<&Path>) -> Vec<PathBuf> {
+  let mut locations = Vec::new();
+  if let Some(exe_dir) = std::env::current_exe().ok().and_then(|exe| exe.parent().map(|p| p.to_path_buf())) {
+    locations.push(exe_dir.join(OVERRIDE_FILE_NAME));
+  }
+  if let Some(app_data_dir) = app_data_dir {
+    locations.push(app_data_dir.join(OVERRIDE_FILE_NAME));
+  }
+  locations
+}
+
+pub fn find_override(locations: &[PathBuf]) -> Option<PathBuf> {
+  locations.iter().find(|path| path.is_file()).cloned()
+}
+
+fn id_of(value: &Value) -> Option<&str> {
+  value.get("id").and_then(|id| id.as_str())
+}
+
+// Objects merge key by key, null removes a key, arrays of objects with an `id`
+// (games, social links) merge entry by entry, anything else is replaced.
+pub fn deep_merge(base: &mut Value, patch: Value) {
+  match (base, patch) {
+    (Value::Object(base), Value::Object(patch)) => {
+      for (key, value) in patch {
+        if value.is_null() {
+          base.remove(&key);
+        } else if let Some(existing) = base.get_mut(&key) {
+          deep_merge(existing, value);
+        } else {
+          base.insert(key, value);
+        }
+      }
+    }
+    (Value::Array(base), Value::Array(patch)) if !patch.is_empty() && patch.iter().all(|v| id_of(v).is_some()) => {
+      for entry in patch {
+        let id = id_of(&entry).unwrap_or_default().to_string();
+        match base.iter_mut().find(|existing| id_of(existing) == Some(id.as_str())) {
+          Some(existing) => deep_merge(existing, entry),
+          None => base.push(entry),
+        }
+      }
+    }
+    (base, patch) => *base = patch,
+  }
+}
+
+pub fn apply_override(manifest: &GameManifest, path: &Path) -> Result<GameManifest, String> {
+  let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+  let patch: Value = serde_json::from_str(&contents).map_err(|e| format!("Invalid JSON in {:?}: {}", path, e))?;
+
+  let mut merged = serde_json::to_value(manifest).map_err(|e| e.to_string())?;
+  deep_merge(&mut merged, patch);
+  serde_json::from_value(merged).map_err(|e| format!("Override {:?} produces an invalid manifest: {}", path, e))
+}
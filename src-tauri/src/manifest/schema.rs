@@ -0,0 +1,133 @@
+use serde_json::{json, Map, Value};
+
+use crate::GameManifest;
+
+// Schema this launcher understands. Minor bumps only add optional fields,
+// a major bump means older launchers cannot read the manifest at all.
+pub const CURRENT_SCHEMA_MAJOR: u64 = 1;
+pub const CURRENT_SCHEMA_VERSION: &str = "1.0";
+
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+// Each entry upgrades a manifest from the given major version to the next one
+const MIGRATIONS: &[(u64, Migration)] = &[
+  (0, migrate_v0_to_v1),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchemaVersion {
+  pub major: u64,
+  pub minor: u64,
+}
+
+pub fn parse_schema_version(version: &str) -> Result<SchemaVersion, String> {
+  let mut parts = version.trim().trim_start_matches('v').split('.');
+  let major = parts.next()
+    .and_then(|p| p.parse::<u64>().ok())
+    .ok_or_else(|| format!("Invalid manifest_version '{}'", version))?;
+  let minor = match parts.next() {
+    Some(p) => p.parse::<u64>().map_err(|_| format!("Invalid manifest_version '{}'", version))?,
+    None => 0,
+  };
+  Ok(SchemaVersion { major, minor })
+}
+
+// Manifests published before manifest_version existed are treated as schema 0
+fn detect_version(value: &Value) -> Result<SchemaVersion, String> {
+  match value.get("manifest_version") {
+    Some(Value::String(v)) => parse_schema_version(v),
+    Some(Value::Number(n)) => parse_schema_version(&n.to_string()),
+    Some(_) => Err("manifest_version must be a string".to_string()),
+    None => Ok(SchemaVersion { major: 0, minor: 0 }),
+  }
+}
+
+// Parse manifest bytes of any supported schema into the current GameManifest
+pub fn parse_manifest(body: &[u8]) -> Result<GameManifest, String> {
+  let value: Value = serde_json::from_slice(body).map_err(|e| format!("Manifest is not valid JSON: {}", e))?;
+  load_manifest_value(value)
+}
+
+pub fn load_manifest_value(mut value: Value) -> Result<GameManifest, String> {
+  let version = detect_version(&value)?;
+  if version.major > CURRENT_SCHEMA_MAJOR {
+    return Err(format!(
+      "Manifest schema {}.{} is newer than this launcher supports ({}.x), please update the launcher",
+      version.major, version.minor, CURRENT_SCHEMA_MAJOR
+    ));
+  }
+  // A numeric manifest_version (1, 1.0) is accepted, but GameManifest stores it as text
+  if let Some(Value::Number(number)) = value.get("manifest_version") {
+    let text = number.to_string();
+    value["manifest_version"] = Value::String(text);
+  }
+
+  let mut major = version.major;
+  while major < CURRENT_SCHEMA_MAJOR {
+    let migration = MIGRATIONS.iter()
+      .find(|(from, _)| *from == major)
+      .map(|(_, migration)| migration)
+      .ok_or_else(|| format!("No migration from manifest schema {}", major))?;
+    migration(&mut value)?;
+    major += 1;
+  }
+
+  serde_json::from_value(value).map_err(|e| format!("Manifest does not match schema {}: {}", CURRENT_SCHEMA_VERSION, e))
+}
+
+fn insert_default(object: &mut Map<String, Value>, key: &str, default: Value) {
+  if !object.contains_key(key) || object[key].is_null() {
+    object.insert(key.to_string(), default);
+  }
+}
+
+// Schema 0: a bare list of games (or an object with only games), no launcher sections,
+// backgrounds as an array and games without the coming-soon / repair flags
+fn migrate_v0_to_v1(value: &mut Value) -> Result<(), String> {
+  if value.is_array() {
+    *value = json!({ "games": value.take() });
+  }
+  let root = value.as_object_mut().ok_or("Legacy manifest must be an object or a list of games")?;
+
+  insert_default(root, "last_updated", json!(""));
+  insert_default(root, "launcher_config", json!({
+    "current_version": "",
+    "update_url": "",
+    "changelog": "",
+    "auto_check_updates": true,
+    "check_interval_hours": 24,
+  }));
+  insert_default(root, "social_links", json!([]));
+  insert_default(root, "settings", json!({
+    "startup_with_windows": false,
+    "minimize_to_tray": false,
+    "auto_check_updates": true,
+    "download_path": "",
+    "max_backups": 3,
+  }));
+  insert_default(root, "games", json!([]));
+
+  if let Some(Value::Array(list)) = root.get("backgrounds").cloned() {
+    let by_id: Map<String, Value> = list.into_iter()
+      .filter_map(|bg| bg.get("id").and_then(|id| id.as_str()).map(|id| (id.to_string(), bg.clone())))
+      .collect();
+    root.insert("backgrounds".to_string(), Value::Object(by_id));
+  }
+  insert_default(root, "backgrounds", json!({}));
+
+  if let Some(Value::Array(games)) = root.get_mut("games") {
+    for game in games.iter_mut() {
+      let game = game.as_object_mut().ok_or("Legacy manifest game entries must be objects")?;
+      let coming_soon = game.get("status").and_then(|s| s.as_str()) == Some("coming_soon");
+      insert_default(game, "status", json!("available"));
+      insert_default(game, "image_url", json!(""));
+      insert_default(game, "background_id", json!(""));
+      insert_default(game, "description", json!(""));
+      insert_default(game, "is_coming_soon", json!(coming_soon));
+      insert_default(game, "repair_enabled", json!(!coming_soon));
+    }
+  }
+
+  root.insert("manifest_version".to_string(), json!("1.0"));
+  Ok(())
+}
@@ -0,0 +1,63 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+// Ed25519 public key (base64, raw 32 bytes) that manifest signatures are checked against, baked
+// in at compile time with ANTCHILL_MANIFEST_PUBLIC_KEY. A release build without it would reject
+// every real manifest, so it fails to compile instead.
+#[cfg(not(debug_assertions))]
+const PUBLIC_KEY: &str = env!(
+  "ANTCHILL_MANIFEST_PUBLIC_KEY",
+  "Release builds need ANTCHILL_MANIFEST_PUBLIC_KEY set to the base64 manifest signing public key"
+);
+
+// Debug builds fall back to a placeholder nobody signs with; use ANTCHILL_ALLOW_UNSIGNED_MANIFEST
+// against local test servers
+#[cfg(debug_assertions)]
+const PUBLIC_KEY: &str = match option_env!("ANTCHILL_MANIFEST_PUBLIC_KEY") {
+  Some(key) => key,
+  None => "T28s5bcYSu8zHMfyOqZehZNoYs2hqRXJAGNK0DC8G4o=",
+};
+
+// Debug builds only: set to 1 to accept manifests without a signature (local test servers)
+pub const ALLOW_UNSIGNED_ENV: &str = "ANTCHILL_ALLOW_UNSIGNED_MANIFEST";
+
+fn verifying_key() -> Result<VerifyingKey, String> {
+  let bytes = BASE64.decode(PUBLIC_KEY.trim())
+    .map_err(|e| format!("Invalid manifest public key: {}", e))?;
+  let bytes: [u8; 32] = bytes.try_into()
+    .map_err(|_| "Invalid manifest public key: expected 32 bytes".to_string())?;
+  VerifyingKey::from_bytes(&bytes).map_err(|e| format!("Invalid manifest public key: {}", e))
+}
+
+// Detached signature lives next to the manifest, e.g. manifest.json.sig
+pub fn signature_url(manifest_url: &str) -> String {
+  format!("{}.sig", manifest_url)
+}
+
+// Accept either the raw 64 byte signature or its base64 text form
+fn decode_signature(raw: &[u8]) -> Result<Signature, String> {
+  if raw.len() == Signature::BYTE_SIZE {
+    let bytes: [u8; 64] = raw.try_into().map_err(|_| "Invalid signature length".to_string())?;
+    return Ok(Signature::from_bytes(&bytes));
+  }
+
+  let text = std::str::from_utf8(raw).map_err(|_| "Signature is neither raw nor base64".to_string())?;
+  let bytes = BASE64.decode(text.trim()).map_err(|e| format!("Invalid signature encoding: {}", e))?;
+  let bytes: [u8; 64] = bytes.try_into()
+    .map_err(|_| "Invalid signature length".to_string())?;
+  Ok(Signature::from_bytes(&bytes))
+}
+
+// Verify the exact manifest bytes against the compiled-in key
+pub fn verify_manifest(body: &[u8], signature: &[u8]) -> Result<(), String> {
+  let signature = decode_signature(signature)?;
+  verifying_key()?
+    .verify(body, &signature)
+    .map_err(|_| "Manifest signature does not match".to_string())
+}
+
+pub fn allow_unsigned() -> bool {
+  cfg!(debug_assertions)
+    && std::env::var(ALLOW_UNSIGNED_ENV).map(|v| v == "1").unwrap_or(false)
+}
@@ -0,0 +1,173 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+use super::{schema, signature, validate};
+use crate::settings::LauncherSettings;
+use crate::GameManifest;
+
+pub const DEFAULT_MANIFEST_URL: &str = "https://pub-72a5a57231ae489cb74409bdc120cb93.r2.dev/manifest.json";
+
+// Comma separated list of manifest URLs, overrides the settings file when set
+pub const MANIFEST_SOURCES_ENV: &str = "ANTCHILL_MANIFEST_URLS";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ManifestSourcesInfo {
+  pub sources: Vec<String>,
+  pub from_env: bool,
+  pub active_source: Option<String>,
+}
+
+// Per-mirror timeout for manifest requests
+pub const FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Manifest together with the mirror that served it
+#[derive(Debug, Clone)]
+pub struct FetchedManifest {
+  pub manifest: GameManifest,
+  // Exact bytes the signature covers, kept so the cached copy can be checked again
+  pub body: Vec<u8>,
+  // None only when an unsigned manifest was allowed
+  pub signature: Option<Vec<u8>>,
+  pub source_url: String,
+  pub etag: Option<String>,
+  pub last_modified: Option<String>,
+}
+
+// Validators from the cached manifest, sent back to the mirror that produced them
+#[derive(Debug, Clone)]
+pub struct CacheValidators {
+  pub source_url: String,
+  pub etag: Option<String>,
+  pub last_modified: Option<String>,
+}
+
+pub enum FetchOutcome {
+  Fresh(Box<FetchedManifest>),
+  // The mirror answered 304, the cached manifest is still current
+  NotModified { source_url: String },
+}
+
+fn parse_source_list(value: &str) -> Vec<String> {
+  value
+    .split([',', ';', '\n'])
+    .map(|s| s.trim().to_string())
+    .filter(|s| !s.is_empty())
+    .collect()
+}
+
+// Ordered mirror list: env override first, then settings, then the built-in default
+pub fn resolve_sources(settings: &LauncherSettings) -> (Vec<String>, bool) {
+  if let Ok(value) = std::env::var(MANIFEST_SOURCES_ENV) {
+    let sources = parse_source_list(&value);
+    if !sources.is_empty() {
+      return (sources, true);
+    }
+  }
+
+  let sources: Vec<String> = settings.manifest_sources.iter()
+    .map(|s| s.trim().to_string())
+    .filter(|s| !s.is_empty())
+    .collect();
+  if sources.is_empty() {
+    return (vec![DEFAULT_MANIFEST_URL.to_string()], false);
+  }
+  (sources, false)
+}
+
+pub async fn fetch_bytes(url: &str, timeout: Duration) -> Result<Option<Vec<u8>>, String> {
+  let response = tokio::time::timeout(timeout, reqwest::get(url))
+    .await
+    .map_err(|_| format!("timed out after {}s", timeout.as_secs()))?
+    .map_err(|e| e.to_string())?;
+
+  if response.status() == reqwest::StatusCode::NOT_FOUND {
+    return Ok(None);
+  }
+  if !response.status().is_success() {
+    return Err(format!("HTTP {}", response.status()));
+  }
+
+  let body = response.bytes().await.map_err(|e| e.to_string())?;
+  Ok(Some(body.to_vec()))
+}
+
+fn header_value(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+  response.headers()
+    .get(name)
+    .and_then(|v| v.to_str().ok())
+    .map(|v| v.to_string())
+}
+
+async fn fetch_from(url: &str, timeout: Duration, validators: Option<&CacheValidators>) -> Result<FetchOutcome, String> {
+  let mut request = reqwest::Client::new().get(url);
+  if let Some(validators) = validators.filter(|v| v.source_url == url) {
+    if let Some(etag) = &validators.etag {
+      request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+      request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+    }
+  }
+
+  let response = tokio::time::timeout(timeout, request.send())
+    .await
+    .map_err(|_| format!("timed out after {}s", timeout.as_secs()))?
+    .map_err(|e| e.to_string())?;
+
+  if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+    return Ok(FetchOutcome::NotModified { source_url: url.to_string() });
+  }
+  if !response.status().is_success() {
+    return Err(format!("HTTP {}", response.status()));
+  }
+
+  let etag = header_value(&response, reqwest::header::ETAG);
+  let last_modified = header_value(&response, reqwest::header::LAST_MODIFIED);
+  let body = response.bytes().await.map_err(|e| e.to_string())?;
+
+  // Only trust manifests signed with the launcher key
+  let sig = fetch_bytes(&signature::signature_url(url), timeout).await?;
+  match &sig {
+    Some(sig) => signature::verify_manifest(&body, sig)?,
+    None if signature::allow_unsigned() => {
+      eprintln!("Accepting unsigned manifest from {} ({} is set)", url, signature::ALLOW_UNSIGNED_ENV);
+    }
+    None => return Err("Manifest is not signed".to_string()),
+  }
+
+  let manifest = schema::parse_manifest(&body)?;
+  for issue in validate::validate_manifest(&manifest).issues {
+    eprintln!("Manifest issue at {}: {}", issue.path, issue.message);
+  }
+  Ok(FetchOutcome::Fresh(Box::new(FetchedManifest {
+    manifest,
+    body: body.to_vec(),
+    signature: sig,
+    source_url: url.to_string(),
+    etag,
+    last_modified,
+  })))
+}
+
+// Try each mirror in order and return the first manifest that loads
+pub async fn fetch_manifest(sources: &[String], validators: Option<&CacheValidators>) -> Result<FetchOutcome, String> {
+  let mut errors = Vec::new();
+
+  for url in sources {
+    match fetch_from(url, FETCH_TIMEOUT, validators).await {
+      Ok(outcome) => {
+        println!("Loaded manifest from {}", url);
+        return Ok(outcome);
+      }
+      Err(e) => {
+        eprintln!("Manifest mirror {} failed: {}", url, e);
+        errors.push(format!("{}: {}", url, e));
+      }
+    }
+  }
+
+  if errors.is_empty() {
+    return Err("No manifest sources configured".to_string());
+  }
+  Err(format!("All manifest mirrors failed ({})", errors.join("; ")))
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::files::is_sha256_hex;
+use super::schema;
+use crate::download::archive::ArchiveFormat;
+use crate::GameManifest;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+  Error,
+  Warning,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ValidationIssue {
+  pub path: String,
+  pub severity: Severity,
+  pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ValidationReport {
+  pub valid: bool,
+  pub error_count: usize,
+  pub warning_count: usize,
+  pub issues: Vec<ValidationIssue>,
+}
+
+struct Issues(Vec<ValidationIssue>);
+
+impl Issues {
+  fn error(&mut self, path: String, message: String) {
+    self.0.push(ValidationIssue { path, severity: Severity::Error, message });
+  }
+
+  fn warning(&mut self, path: String, message: String) {
+    self.0.push(ValidationIssue { path, severity: Severity::Warning, message });
+  }
+
+  fn into_report(self) -> ValidationReport {
+    let error_count = self.0.iter().filter(|i| i.severity == Severity::Error).count();
+    let warning_count = self.0.len() - error_count;
+    ValidationReport {
+      valid: error_count == 0,
+      error_count,
+      warning_count,
+      issues: self.0,
+    }
+  }
+}
+
+// Sizes as published in the manifest, e.g. "37.54 MB" or "1.3GB"
+pub fn parse_file_size(size: &str) -> Option<u64> {
+  let size = size.trim();
+  let split = size.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(size.len());
+  let number: f64 = size[..split].parse().ok()?;
+  let multiplier = match size[split..].trim().to_ascii_uppercase().as_str() {
+    "" | "B" => 1.0,
+    "KB" | "K" => 1024.0,
+    "MB" | "M" => 1024.0 * 1024.0,
+    "GB" | "G" => 1024.0 * 1024.0 * 1024.0,
+    _ => return None,
+  };
+  if number < 0.0 {
+    return None;
+  }
+  Some((number * multiplier) as u64)
+}
+
+// Release dates are written either ISO style or day-month-year
+pub fn parse_release_date(date: &str) -> Option<NaiveDate> {
+  ["%Y-%m-%d", "%d-%m-%Y", "%d/%m/%Y"]
+    .iter()
+    .find_map(|format| NaiveDate::parse_from_str(date.trim(), format).ok())
+}
+
+fn check_archive(issues: &mut Issues, path: &str, sha256: &Option<String>, size: Option<u64>, archive_format: &Option<String>, downloadable: bool) {
+  if let Some(format) = archive_format {
+    if ArchiveFormat::from_name(format).is_none() {
+      issues.error(format!("{}.archive_format", path), format!("Unsupported archive format '{}' (use zip, tar.gz, tar.zst or 7z)", format));
+    }
+  }
+  match sha256 {
+    Some(hash) if !is_sha256_hex(hash.trim()) => {
+      issues.error(format!("{}.sha256", path), format!("Not a hex SHA-256 digest: {}", hash));
+    }
+    None if downloadable => {
+      issues.warning(format!("{}.sha256", path), "No sha256, downloads cannot be verified".to_string());
+    }
+    _ => {}
+  }
+  if size == Some(0) {
+    issues.error(format!("{}.size", path), "Archive size cannot be 0".to_string());
+  }
+}
+
+fn check_file_manifest(issues: &mut Issues, path: &str, files_url: &Option<String>, files_sha256: &Option<String>) {
+  match (files_url, files_sha256) {
+    (Some(url), sha256) => {
+      check_https(issues, format!("{}.files_url", path), url);
+      match sha256 {
+        Some(hash) if !is_sha256_hex(hash.trim()) => {
+          issues.error(format!("{}.files_sha256", path), format!("Not a hex SHA-256 digest: {}", hash));
+        }
+        None => issues.error(format!("{}.files_sha256", path), "No files_sha256, the launcher ignores a file manifest it cannot verify".to_string()),
+        _ => {}
+      }
+    }
+    (None, Some(_)) => issues.warning(format!("{}.files_sha256", path), "files_sha256 has no files_url".to_string()),
+    (None, None) => {}
+  }
+}
+
+fn check_https(issues: &mut Issues, path: String, url: &str) {
+  if url.is_empty() {
+    return;
+  }
+  if !url.starts_with("https://") {
+    issues.error(path, format!("URL must use HTTPS: {}", url));
+  }
+}
+
+// Check a manifest file as published, before signing and uploading it
+pub fn validate_manifest_bytes(body: &[u8]) -> ValidationReport {
+  let mut issues = Issues(Vec::new());
+
+  let value: Value = match serde_json::from_slice(body) {
+    Ok(value) => value,
+    Err(e) => {
+      issues.error("$".to_string(), format!("Invalid JSON: {}", e));
+      return issues.into_report();
+    }
+  };
+
+  match schema::load_manifest_value(value) {
+    Ok(manifest) => check_manifest(&manifest, &mut issues),
+    Err(e) => issues.error("$".to_string(), e),
+  }
+  issues.into_report()
+}
+
+pub fn validate_manifest(manifest: &GameManifest) -> ValidationReport {
+  let mut issues = Issues(Vec::new());
+  check_manifest(manifest, &mut issues);
+  issues.into_report()
+}
+
+fn check_manifest(manifest: &GameManifest, issues: &mut Issues) {
+  if !manifest.last_updated.is_empty() && DateTime::parse_from_rfc3339(&manifest.last_updated).is_err() {
+    issues.error("last_updated".to_string(), format!("Not an RFC 3339 timestamp: {}", manifest.last_updated));
+  }
+  check_https(issues, "launcher_config.update_url".to_string(), &manifest.launcher_config.update_url);
+
+  for (key, background) in &manifest.backgrounds {
+    let path = format!("backgrounds.{}", key);
+    if background.id != *key {
+      issues.error(format!("{}.id", path), format!("Background id '{}' does not match its key '{}'", background.id, key));
+    }
+    check_https(issues, format!("{}.image_url", path), &background.image_url);
+  }
+
+  let mut seen_ids: HashMap<&str, usize> = HashMap::new();
+  for (index, game) in manifest.games.iter().enumerate() {
+    let path = format!("games[{}]", index);
+
+    if game.id.trim().is_empty() {
+      issues.error(format!("{}.id", path), "Game id is empty".to_string());
+    } else {
+      let first = *seen_ids.entry(game.id.as_str()).or_insert(index);
+      if first != index {
+        issues.error(format!("{}.id", path), format!("Duplicate game id '{}' (first used at games[{}])", game.id, first));
+      }
+    }
+
+    if !manifest.backgrounds.contains_key(&game.background_id) {
+      issues.error(format!("{}.background_id", path), format!("Background '{}' is not defined in backgrounds", game.background_id));
+    }
+
+    let coming_soon = game.is_coming_soon || game.status == "coming_soon";
+    if game.is_coming_soon != (game.status == "coming_soon") {
+      issues.warning(format!("{}.status", path), format!("status '{}' disagrees with is_coming_soon = {}", game.status, game.is_coming_soon));
+    }
+    match &game.download_url {
+      Some(url) if coming_soon => {
+        issues.error(format!("{}.download_url", path), format!("Coming-soon game must not have a download URL: {}", url));
+      }
+      Some(url) => check_https(issues, format!("{}.download_url", path), url),
+      None if !coming_soon => {
+        issues.error(format!("{}.download_url", path), "Released game has no download URL".to_string());
+      }
+      None => {}
+    }
+
+    check_archive(issues, &path, &game.sha256, game.size, &game.archive_format, !coming_soon && game.download_url.is_some());
+    check_file_manifest(issues, &path, &game.files_url, &game.files_sha256);
+    check_https(issues, format!("{}.image_url", path), &game.image_url);
+    if let Some(logo_url) = &game.logo_url {
+      check_https(issues, format!("{}.logo_url", path), logo_url);
+    }
+
+    if let Some(date) = &game.release_date {
+      if parse_release_date(date).is_none() {
+        issues.error(format!("{}.release_date", path), format!("Unrecognized date '{}' (use YYYY-MM-DD or DD-MM-YYYY)", date));
+      }
+    }
+    if let Some(size) = &game.file_size {
+      if parse_file_size(size).is_none() {
+        issues.error(format!("{}.file_size", path), format!("Unrecognized size '{}' (e.g. \"37.54 MB\")", size));
+      }
+    }
+
+    for (version_index, version) in game.versions.iter().enumerate() {
+      let version_path = format!("{}.versions[{}]", path, version_index);
+      if version.version == game.version && version.channel == game.channel {
+        issues.warning(format!("{}.version", version_path), format!("Duplicates the top-level {} build {}", game.channel.as_str(), game.version));
+      }
+      if let Some(url) = &version.download_url {
+        check_https(issues, format!("{}.download_url", version_path), url);
+      }
+      check_archive(issues, &version_path, &version.sha256, version.size, &version.archive_format, version.download_url.is_some());
+      check_file_manifest(issues, &version_path, &version.files_url, &version.files_sha256);
+      if let Some(date) = &version.release_date {
+        if parse_release_date(date).is_none() {
+          issues.error(format!("{}.release_date", version_path), format!("Unrecognized date '{}' (use YYYY-MM-DD or DD-MM-YYYY)", date));
+        }
+      }
+      if let Some(size) = &version.file_size {
+        if parse_file_size(size).is_none() {
+          issues.error(format!("{}.file_size", version_path), format!("Unrecognized size '{}' (e.g. \"37.54 MB\")", size));
+        }
+      }
+    }
+  }
+
+  let mut seen_links: HashMap<&str, usize> = HashMap::new();
+  for (index, link) in manifest.social_links.iter().enumerate() {
+    let path = format!("social_links[{}]", index);
+    let first = *seen_links.entry(link.id.as_str()).or_insert(index);
+    if first != index {
+      issues.warning(format!("{}.id", path), format!("Duplicate social link id '{}' (first used at social_links[{}])", link.id, first));
+    }
+    // Action links and mail links are not web URLs
+    if link.action.is_none() && !link.url.starts_with("mailto:") {
+      check_https(issues, format!("{}.url", path), &link.url);
+    }
+  }
+}
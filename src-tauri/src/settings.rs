@@ -0,0 +1,92 @@
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::download::extract::ExtractionMode;
+use crate::download::throttle::DownloadWindow;
+use crate::manifest::channels::ReleaseChannel;
+use crate::manifest::source::DEFAULT_MANIFEST_URL;
+
+pub const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 2;
+pub const DEFAULT_DOWNLOAD_SEGMENTS: usize = 4;
+pub const MAX_DOWNLOAD_SEGMENTS: usize = 16;
+
+// Launcher settings chosen by the user, stored next to the other app config
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct LauncherSettings {
+  // Manifest mirrors, tried in order until one answers
+  pub manifest_sources: Vec<String>,
+  // Which builds the player opted into
+  pub release_channel: ReleaseChannel,
+  // How many queued downloads run at the same time
+  pub max_concurrent_downloads: usize,
+  // Parallel byte-range requests per large archive; 1 downloads in a single stream
+  pub download_segments: usize,
+  // Bandwidth shared by all downloads in KiB/s; 0 is unlimited
+  pub download_speed_limit_kbps: u64,
+  // When set, queued downloads only start inside this window and run unthrottled there
+  pub download_window: Option<DownloadWindow>,
+  // Whether archives are stored before extraction or extracted while downloading
+  pub extraction_mode: ExtractionMode,
+  // Developer opt-in to merge an unsigned manifest.override.json over the signed manifest
+  pub manifest_override_enabled: bool,
+}
+
+impl Default for LauncherSettings {
+  fn default() -> Self {
+    LauncherSettings {
+      manifest_sources: vec![DEFAULT_MANIFEST_URL.to_string()],
+      release_channel: ReleaseChannel::Stable,
+      max_concurrent_downloads: DEFAULT_MAX_CONCURRENT_DOWNLOADS,
+      download_segments: DEFAULT_DOWNLOAD_SEGMENTS,
+      download_speed_limit_kbps: 0,
+      download_window: None,
+      extraction_mode: ExtractionMode::Cached,
+      manifest_override_enabled: false,
+    }
+  }
+}
+
+// Get settings file path
+fn get_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+  let config_dir = app.path_resolver()
+    .app_config_dir()
+    .ok_or("Failed to get app config directory")?;
+  Ok(config_dir.join("launcher_settings.json"))
+}
+
+// Load settings from disk, falling back to defaults when missing or unreadable
+pub fn load_settings(app: &AppHandle) -> LauncherSettings {
+  let path = match get_settings_path(app) {
+    Ok(path) => path,
+    Err(e) => {
+      eprintln!("Failed to resolve settings path: {}", e);
+      return LauncherSettings::default();
+    }
+  };
+
+  match fs::read_to_string(&path) {
+    Ok(contents) => match serde_json::from_str(&contents) {
+      Ok(settings) => settings,
+      Err(e) => {
+        eprintln!("Failed to parse launcher settings, using defaults: {}", e);
+        LauncherSettings::default()
+      }
+    },
+    Err(_) => LauncherSettings::default(),
+  }
+}
+
+// Save settings to disk
+pub fn save_settings(app: &AppHandle, settings: &LauncherSettings) -> Result<(), String> {
+  let path = get_settings_path(app)?;
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+  }
+
+  let settings_json = serde_json::to_string_pretty(settings)
+    .map_err(|e| e.to_string())?;
+  fs::write(path, settings_json).map_err(|e| e.to_string())
+}
@@ -0,0 +1,172 @@
+use std::cmp::Ordering;
+use serde::{Deserialize, Serialize};
+
+// A parsed game or launcher version. Accepts semver ("2.3.0", "1.0.0-beta.2+build5") as well as
+// the looser styles in our manifests: a leading "v", any number of components ("0.01", "1.0")
+// and leading zeros. Components compare numerically with missing ones counting as zero, so
+// "0.01", "0.1" and "0.1.0" are the same version.
+#[derive(Debug, Clone)]
+pub struct Version {
+  components: Vec<u64>,
+  // Dot-separated identifiers after "-"; a pre-release sorts before its release
+  pre_release: Vec<String>,
+}
+
+impl Version {
+  pub fn parse(value: &str) -> Option<Version> {
+    let value = value.trim();
+    let value = value.strip_prefix('v').or_else(|| value.strip_prefix('V')).unwrap_or(value);
+    // Build metadata never affects ordering
+    let value = value.split('+').next().unwrap_or(value);
+    let (core, pre_release) = match value.split_once('-') {
+      Some((core, pre)) => (core, Some(pre)),
+      None => (value, None),
+    };
+
+    let components = core
+      .split('.')
+      .map(|part| {
+        if !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()) {
+          part.parse::<u64>().ok()
+        } else {
+          None
+        }
+      })
+      .collect::<Option<Vec<u64>>>()?;
+
+    let pre_release = match pre_release {
+      Some(pre) => {
+        let identifiers: Vec<String> = pre.split('.').map(|s| s.to_string()).collect();
+        if identifiers.iter().any(|s| s.is_empty()) {
+          return None;
+        }
+        identifiers
+      }
+      None => Vec::new(),
+    };
+
+    Some(Version { components, pre_release })
+  }
+}
+
+// Semver precedence: numeric identifiers compare as numbers and sort before alphanumeric ones,
+// and a longer list wins when one is a prefix of the other
+fn compare_pre_release(a: &[String], b: &[String]) -> Ordering {
+  match (a.is_empty(), b.is_empty()) {
+    (true, true) => return Ordering::Equal,
+    (true, false) => return Ordering::Greater,
+    (false, true) => return Ordering::Less,
+    _ => {}
+  }
+  for (x, y) in a.iter().zip(b.iter()) {
+    let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+      (Ok(x), Ok(y)) => x.cmp(&y),
+      (Ok(_), Err(_)) => Ordering::Less,
+      (Err(_), Ok(_)) => Ordering::Greater,
+      (Err(_), Err(_)) => x.cmp(y),
+    };
+    if ordering != Ordering::Equal {
+      return ordering;
+    }
+  }
+  a.len().cmp(&b.len())
+}
+
+impl Ord for Version {
+  fn cmp(&self, other: &Self) -> Ordering {
+    for i in 0..self.components.len().max(other.components.len()) {
+      let a = self.components.get(i).copied().unwrap_or(0);
+      let b = other.components.get(i).copied().unwrap_or(0);
+      if a != b {
+        return a.cmp(&b);
+      }
+    }
+    compare_pre_release(&self.pre_release, &other.pre_release)
+  }
+}
+
+impl PartialEq for Version {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+// Order two version strings. Versions that do not parse sort before any that do and fall back
+// to a plain string comparison among themselves.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+  match (Version::parse(a), Version::parse(b)) {
+    (Some(a), Some(b)) => a.cmp(&b),
+    (Some(_), None) => Ordering::Greater,
+    (None, Some(_)) => Ordering::Less,
+    (None, None) => a.trim().cmp(b.trim()),
+  }
+}
+
+// How an installed version relates to the one offered by the manifest
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateStatus {
+  UpdateAvailable,
+  UpToDate,
+  // The server rolled back, or a newer local build is installed
+  LocalNewer,
+}
+
+impl UpdateStatus {
+  pub fn between(installed: &str, latest: &str) -> UpdateStatus {
+    match compare_versions(installed, latest) {
+      Ordering::Less => UpdateStatus::UpdateAvailable,
+      Ordering::Equal => UpdateStatus::UpToDate,
+      Ordering::Greater => UpdateStatus::LocalNewer,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn loose_versions_compare_numerically() {
+    assert_eq!(compare_versions("0.01", "0.1"), Ordering::Equal);
+    assert_eq!(compare_versions("0.1", "0.1.0"), Ordering::Equal);
+    assert_eq!(compare_versions("v1.2", "1.2"), Ordering::Equal);
+    assert_eq!(compare_versions("0.10", "0.9"), Ordering::Greater);
+    assert_eq!(compare_versions("1.2", "1.10"), Ordering::Less);
+    assert_eq!(compare_versions("1.0.0+build5", "1.0.0"), Ordering::Equal);
+  }
+
+  #[test]
+  fn pre_release_sorts_before_release() {
+    let ordered = ["1.0.0-alpha", "1.0.0-alpha.1", "1.0.0-beta", "1.0.0-beta.2", "1.0.0-beta.11", "1.0.0-rc.1", "1.0.0", "1.0.1-beta"];
+    for pair in ordered.windows(2) {
+      assert_eq!(compare_versions(pair[0], pair[1]), Ordering::Less, "{} < {}", pair[0], pair[1]);
+    }
+  }
+
+  #[test]
+  fn unparseable_versions_sort_first() {
+    for invalid in ["", "1..2", "1.x", "1.0-", "latest"] {
+      assert!(Version::parse(invalid).is_none(), "{}", invalid);
+    }
+    assert_eq!(compare_versions("latest", "0.0.1"), Ordering::Less);
+    assert_eq!(compare_versions("beta", "alpha"), Ordering::Greater);
+  }
+
+  #[test]
+  fn update_status() {
+    assert_eq!(UpdateStatus::between("0.01", "0.1"), UpdateStatus::UpToDate);
+    assert_eq!(UpdateStatus::between("1.2", "1.10"), UpdateStatus::UpdateAvailable);
+    assert_eq!(UpdateStatus::between("1.0.0-rc.1", "1.0.0"), UpdateStatus::UpdateAvailable);
+    // The server rolled back to an older build
+    assert_eq!(UpdateStatus::between("2.0", "1.9"), UpdateStatus::LocalNewer);
+  }
+}
@@ -1,5 +1,6 @@
 import React, { useState, useEffect, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/tauri';
+import { listen } from '@tauri-apps/api/event';
 import { open } from '@tauri-apps/api/shell';
 import { useLanguage } from './hooks/useLanguage';
 import './App.css';
@@ -11,6 +12,8 @@ interface GameInfo {
   status: string;
   download_url?: string;
   executable_path?: string;
+  // Build found on disk by scan_local_games; `version` is the manifest's
+  installed_version?: string;
   image_url: string;
   logo_url?: string;
   background_id: string;
@@ -25,11 +28,27 @@ interface GameInfo {
 interface UpdateInfo {
   current_version: string;
   latest_version: string;
+  status: 'update_available' | 'up_to_date' | 'local_newer';
   needs_update: boolean;
   update_url?: string;
   changelog?: string;
 }
 
+interface LauncherCompatibility {
+  compatible: boolean;
+  current_version: string;
+  required_version?: string;
+  update_url?: string;
+  message?: string;
+}
+
+interface InstallResult {
+  install_path: string;
+  files_extracted: number;
+  bytes_extracted: number;
+  rejected_entries: { name: string; reason: string }[];
+}
+
 interface RepairResult {
   success: boolean;
   repaired_files: string[];
@@ -42,13 +61,26 @@ interface NetworkStatus {
   message: string;
 }
 
+interface DownloadProgressEvent {
+  game_id: string;
+  phase: 'waiting' | 'downloading' | 'verifying' | 'extracting' | 'finalizing' | 'completed';
+  bytes_downloaded: number;
+  total_bytes?: number;
+  percent?: number;
+  speed_bytes_per_sec: number;
+  eta_seconds?: number;
+  // Set while waiting for the download window to open
+  window_start?: string;
+}
+
 interface DownloadProgress {
   gameId: string;
   progress: number;
   speed: string;
   downloaded: string;
   total: string;
-  status: 'downloading' | 'extracting' | 'completed' | 'error';
+  status: 'waiting' | 'downloading' | 'extracting' | 'completed' | 'error';
+  windowStart?: string;
 }
 
 function App() {
@@ -75,6 +107,7 @@ function App() {
     const saved = localStorage.getItem('launcher-startup-with-windows');
     return saved === 'true';
   });
+  const [launcherCompatibility, setLauncherCompatibility] = useState<LauncherCompatibility | null>(null);
   const headerRef = useRef<HTMLDivElement>(null);
 
   useEffect(() => {
@@ -138,6 +171,15 @@ function App() {
     localStorage.setItem('launcher-startup-with-windows', startupWithWindows.toString());
   }, [startupWithWindows]);
 
+  // Builds that need a newer launcher link to the launcher update instead of offering Install
+  useEffect(() => {
+    setLauncherCompatibility(null);
+    if (selectedGame?.status !== 'launcher_update_required') return;
+    invoke<LauncherCompatibility>('check_launcher_compatibility', { gameId: selectedGame.id })
+      .then(setLauncherCompatibility)
+      .catch(err => console.error('Compatibility check failed:', err));
+  }, [selectedGame?.id, selectedGame?.status]);
+
   // Check connection periodically
   useEffect(() => {
     const checkConnectionInterval = setInterval(async () => {
@@ -212,8 +254,9 @@ function App() {
     }
   };
 
-  const handleDownloadGame = async (game: GameInfo) => {
-    if (!game.download_url) return;
+  // Updates go through download_game_update, which backs up the installed build first
+  const handleDownloadGame = async (game: GameInfo, isUpdate = false) => {
+    if (!game.download_url || game.status === 'launcher_update_required') return;
     
     setDownloading(game.id);
     setDownloadProgress({
@@ -225,35 +268,35 @@ function App() {
       status: 'downloading'
     });
 
+    const formatBytes = (bytes: number) => `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+
+    // Real progress reported by the backend while download_game runs
+    const unlistenProgress = await listen<DownloadProgressEvent>('download-progress', event => {
+      const payload = event.payload;
+      if (payload.game_id !== game.id) return;
+
+      setDownloadProgress(prev => prev ? {
+        ...prev,
+        progress: payload.percent ?? prev.progress,
+        speed: `${(payload.speed_bytes_per_sec / (1024 * 1024)).toFixed(1)} MB/s`,
+        downloaded: formatBytes(payload.bytes_downloaded),
+        total: payload.total_bytes ? formatBytes(payload.total_bytes) : prev.total,
+        status: payload.phase === 'waiting' ? 'waiting'
+          : payload.phase === 'downloading' ? 'downloading'
+          : payload.phase === 'completed' ? 'completed'
+          : 'extracting',
+        windowStart: payload.window_start
+      } : null);
+    });
+
     try {
-      // Simulate download progress
-      const progressInterval = setInterval(() => {
-        setDownloadProgress(prev => {
-          if (!prev) return prev;
-          const newProgress = Math.min(prev.progress + Math.random() * 10, 95);
-          const speed = (Math.random() * 5 + 1).toFixed(1) + ' MB/s';
-          const downloaded = ((newProgress / 100) * parseFloat(prev.total.replace(' MB', ''))).toFixed(1) + ' MB';
-          
-          return {
-            ...prev,
-            progress: newProgress,
-            speed,
-            downloaded
-          };
-        });
-      }, 500);
-
-      await invoke('download_game', { 
+      const installResult = await invoke<InstallResult>(isUpdate ? 'download_game_update' : 'download_game', { 
         gameId: game.id, 
         downloadUrl: game.download_url 
       });
-
-      clearInterval(progressInterval);
-      
-      setDownloadProgress(prev => prev ? { ...prev, status: 'extracting', progress: 100 } : null);
-      
-      // Simulate extraction
-      await new Promise(resolve => setTimeout(resolve, 2000));
+      if (installResult.rejected_entries.length > 0) {
+        console.warn('Skipped unsafe archive entries:', installResult.rejected_entries);
+      }
       
       // Refresh games list and scan for installed game
       const updatedGames = await invoke<GameInfo[]>('get_games');
@@ -266,7 +309,7 @@ function App() {
         // Game is now installed and ready to play
         console.log('Game installed successfully:', installedGame.name);
         // Update selectedGame with new executable_path
-        setSelectedGame(prev => prev ? { ...prev, executable_path: installedGame.executable_path, status: 'available' } : null);
+        setSelectedGame(prev => prev ? { ...prev, executable_path: installedGame.executable_path, installed_version: installedGame.installed_version, status: 'available' } : null);
       }
       
       // Clear download progress immediately after extraction
@@ -278,6 +321,8 @@ function App() {
       setDownloadProgress(null);
       // Show error message to user
       alert(`Download failed: ${err}`);
+    } finally {
+      unlistenProgress();
     }
   };
 
@@ -297,13 +342,15 @@ function App() {
     try {
       const updateInfo: UpdateInfo = await invoke('check_game_updates', {
         gameId: game.id,
-        currentVersion: game.version
+        currentVersion: game.installed_version ?? game.version
       });
       
-      if (updateInfo.needs_update) {
+      if (updateInfo.status === 'update_available') {
         if (confirm(`Update available: ${updateInfo.latest_version}\n\n${updateInfo.changelog || 'No changelog available'}\n\nUpdate now?`)) {
-          await handleDownloadGame(game);
+          await handleDownloadGame(game, true);
         }
+      } else if (updateInfo.status === 'local_newer') {
+        alert(`Installed version ${updateInfo.current_version} is newer than the latest release (${updateInfo.latest_version})`);
       } else {
         alert('Game is up to date!');
       }
@@ -730,7 +777,9 @@ function App() {
                 <h4>{game.name}</h4>
                 <p>v{game.version}</p>
                 <span className={`status ${game.status}`}>
-                  {game.status === 'available' ? t('launcher.games.available') : t('launcher.games.coming_soon')}
+                  {game.status === 'available' ? t('launcher.games.available')
+                    : game.status === 'launcher_update_required' ? t('launcher.games.launcher_update_required')
+                    : t('launcher.games.coming_soon')}
                 </span>
               </div>
             </div>
@@ -756,12 +805,39 @@ function App() {
                   ) : (
                     <>
                       {selectedGame.executable_path ? (
-                        <button 
-                          className="btn-play"
-                          onClick={() => handleLaunchGame(selectedGame)}
-                        >
-                          {t('launcher.games.play')}
-                        </button>
+                        <>
+                          <button 
+                            className="btn-play"
+                            onClick={() => handleLaunchGame(selectedGame)}
+                          >
+                            {t('launcher.games.play')}
+                          </button>
+                          <button
+                            className="btn-install"
+                            onClick={() => handleCheckUpdates(selectedGame)}
+                            disabled={downloading === selectedGame.id}
+                          >
+                            {t('launcher.games.check_updates')}
+                          </button>
+                        </>
+                      ) : selectedGame.status === 'launcher_update_required' ? (
+                        <>
+                          <button
+                            className="btn-coming-soon"
+                            disabled
+                            title={launcherCompatibility?.message}
+                          >
+                            {t('launcher.games.launcher_update_required')}
+                          </button>
+                          {launcherCompatibility?.update_url && (
+                            <button
+                              className="btn-install"
+                              onClick={() => open(launcherCompatibility.update_url!)}
+                            >
+                              {t('launcher.games.update_launcher')}
+                            </button>
+                          )}
+                        </>
                       ) : (
                         <button 
                           className="btn-install"
@@ -772,7 +848,8 @@ function App() {
                             {downloading === selectedGame.id ? (
                               <>
                                 <span className="progress-text">
-                                  {downloadProgress?.status === 'extracting' ? 'Extracting...' : 
+                                  {downloadProgress?.status === 'waiting' ? `${t('launcher.games.waiting_for_window')} ${downloadProgress.windowStart ?? ''}` :
+                                   downloadProgress?.status === 'extracting' ? 'Extracting...' : 
                                    `${downloadProgress?.progress.toFixed(0) || 0}%`}
                                 </span>
                                 {downloadProgress?.status === 'downloading' && (
@@ -24,7 +24,10 @@ const translations: Record<Language, Translations> = {
         "downloading": "⏳ Downloading...",
         "check_updates": "🔄 Check Updates",
         "repair": "🔧 Repair",
-        "coming_soon_btn": "🕐 Coming Soon"
+        "coming_soon_btn": "🕐 Coming Soon",
+        "launcher_update_required": "⚠️ Launcher update required",
+        "waiting_for_window": "⏰ Waiting for download window",
+        "update_launcher": "⬆️ Update Launcher"
       },
       "settings": {
         "title": "Settings",
@@ -75,7 +78,10 @@ const translations: Record<Language, Translations> = {
         "downloading": "⏳ Đang tải...",
         "check_updates": "🔄 Kiểm tra cập nhật",
         "repair": "🔧 Sửa chữa",
-        "coming_soon_btn": "🕐 Sắp ra mắt"
+        "coming_soon_btn": "🕐 Sắp ra mắt",
+        "launcher_update_required": "⚠️ Cần cập nhật launcher",
+        "waiting_for_window": "⏰ Chờ đến khung giờ tải",
+        "update_launcher": "⬆️ Cập nhật launcher"
       },
       "settings": {
         "title": "Cài đặt",